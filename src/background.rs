@@ -0,0 +1,218 @@
+use macroquad::prelude::*;
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
+use noise::Perlin;
+
+use super::camera::*;
+use super::mathtools::*;
+use super::texturetools::*;
+
+// star_system.rs uses 0-3 off the system seed
+const BACKGROUND_SALT: u64 = 8;
+
+// at this zoom every layer is drawn at its own size. zooming in
+// from here makes the nearer layers bigger, out makes them smaller
+const REFERENCE_ZOOM: f64 = 0.1;
+
+const STAR_TILE: u16 = 512;
+const NEBULA_TILE: u16 = 256;
+
+// (how far the layer moves compared to the bodies, stars per tile,
+// biggest star radius in texels, brightness). Far to near.
+const STAR_LAYERS: [(f32, usize, f32, f32); 3] = [
+    (0.02, 900, 0.9, 0.55),
+    (0.06, 300, 1.4, 0.8),
+    (0.15, 90, 2.2, 1.),
+];
+const NEBULA_PARALLAX: f32 = 0.01;
+// nebula texels are blown up this much, it's all soft anyway
+const NEBULA_SCALE: f32 = 4.;
+
+// not everything out there is white
+const STAR_TINTS: [(f32, f32, f32); 5] = [
+    (1., 1., 1.),
+    (0.8, 0.87, 1.),
+    (1., 0.95, 0.8),
+    (1., 0.8, 0.65),
+    (0.7, 0.8, 1.),
+];
+const NEBULA_COLORS: [(f32, f32, f32); 5] = [
+    (0.45, 0.2, 0.6),
+    (0.15, 0.45, 0.55),
+    (0.6, 0.2, 0.25),
+    (0.2, 0.3, 0.7),
+    (0.55, 0.4, 0.2),
+];
+
+// One repeating picture scrolled along behind the system
+struct Layer {
+    texture: Texture2D,
+    // how far it moves compared to the bodies in front. 0 would be
+    // painted on the screen, 1 right in there with them
+    parallax: f32,
+    // screen pixels per texel at REFERENCE_ZOOM
+    scale: f32,
+    // how far it's scrolled, in screen pixels
+    offset: Vec2,
+}
+
+impl Layer {
+    fn new(image: &Image, parallax: f32, scale: f32) -> Layer {
+        Layer {
+            texture: Texture2D::from_image(image),
+            parallax,
+            scale,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    // tiles the texture over the whole screen
+    fn draw(&self, zoom: f64) {
+        let scale = self.scale * (zoom / REFERENCE_ZOOM).powf(self.parallax as f64 * 0.5) as f32;
+        let tile = self.texture.size() * scale;
+        if tile.x < 1. || tile.y < 1. {
+            return;
+        }
+        let start = vec2(self.offset.x.rem_euclid(tile.x) - tile.x, self.offset.y.rem_euclid(tile.y) - tile.y);
+        let mut y = start.y;
+        while y < screen_height() {
+            let mut x = start.x;
+            while x < screen_width() {
+                draw_texture_ex(
+                    &self.texture,
+                    x,
+                    y,
+                    WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(tile),
+                        ..Default::default()
+                    },
+                );
+                x += tile.x;
+            }
+            y += tile.y;
+        }
+    }
+}
+
+// What's behind a star system: a nebula and a few starfields, each
+// further off than the last so they slide by slower as the camera
+// moves. Made from the system's seed, so each system has its own sky
+// and going back to one gives the same sky again.
+pub struct Background {
+    pub seed: u64,
+    // drawn in order, so furthest first
+    layers: Vec<Layer>,
+    // world point the camera was centered on last frame
+    last_center: Option<(f32, f32)>,
+}
+
+impl Background {
+    // nothing at all, for before there's a system
+    pub fn empty() -> Background {
+        Background {
+            seed: 0,
+            layers: Vec::new(),
+            last_center: None,
+        }
+    }
+
+    pub fn new(system_seed: u64) -> Background {
+        let seed = sub_seed(system_seed, BACKGROUND_SALT);
+        let mut layers = vec![Layer::new(&nebula_image(sub_seed(seed, 0)), NEBULA_PARALLAX, NEBULA_SCALE)];
+        for (i, &(parallax, count, size, brightness)) in STAR_LAYERS.iter().enumerate() {
+            let image = starfield_image(sub_seed(seed, i as u64 + 1), count, size, brightness);
+            layers.push(Layer::new(&image, parallax, 1.));
+        }
+        for layer in layers.iter() {
+            layer.texture.set_filter(FilterMode::Nearest);
+        }
+        // the nebula's blown up, it needs the smoothing
+        layers[0].texture.set_filter(FilterMode::Linear);
+        Background {
+            seed: system_seed,
+            layers,
+            last_center: None,
+        }
+    }
+
+    // Scrolls each layer by however far the camera's moved since
+    // last frame (scaled down by how far away it is), then draws.
+    // Goes by how far the view moved rather than where it is, so
+    // zooming doesn't drag the sky around with it.
+    pub fn draw(&mut self, camera: &ZCamera) {
+        let center = camera.center();
+        let moved = match self.last_center {
+            Some((x, y)) => vec2(center.0 - x, center.1 - y) * camera.zoom as f32,
+            None => Vec2::ZERO,
+        };
+        self.last_center = Some(center);
+        for layer in self.layers.iter_mut() {
+            layer.offset -= moved * layer.parallax;
+            layer.draw(camera.zoom);
+        }
+    }
+}
+
+// Soft dots scattered over a tile. Dots hanging off one edge come
+// back in on the other, so tiles sit next to each other without
+// a seam.
+fn starfield_image(seed: u64, count: usize, max_size: f32, brightness: f32) -> Image {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let tile = STAR_TILE as i32;
+    let mut image = Image::gen_image_color(STAR_TILE, STAR_TILE, Color::new(0., 0., 0., 0.));
+    for _ in 0..count {
+        let (x, y) = (rng.gen_range(0..tile), rng.gen_range(0..tile));
+        let size: f32 = rng.gen_range(0.5..max_size.max(0.6));
+        let glow = brightness * rng.gen_range(0.35..1.);
+        let (r, g, b) = STAR_TINTS[rng.gen_range(0..STAR_TINTS.len())];
+        let reach = size.ceil() as i32 + 1;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let d = ((dx * dx + dy * dy) as f32).sqrt();
+                let alpha = (1. - d / (size + 0.5)).clamp(0., 1.) * glow;
+                if alpha <= 0. {
+                    continue;
+                }
+                let (px, py) = ((x + dx).rem_euclid(tile) as u32, (y + dy).rem_euclid(tile) as u32);
+                // overlapping stars keep the brighter one
+                if image.get_pixel(px, py).a < alpha {
+                    image.set_pixel(px, py, Color::new(r, g, b, alpha));
+                }
+            }
+        }
+    }
+    image
+}
+
+// Noise clouds in two colors, made to tile both ways
+fn nebula_image(seed: u64) -> Image {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let first = rng.gen_range(0..NEBULA_COLORS.len());
+    let second = (first + rng.gen_range(1..NEBULA_COLORS.len())) % NEBULA_COLORS.len();
+    let ((r0, g0, b0), (r1, g1, b1)) = (NEBULA_COLORS[first], NEBULA_COLORS[second]);
+    // some skies are a lot emptier than others
+    let thickness: f32 = rng.gen_range(0.25..0.7);
+    let shape = Perlin::new(perlin_seed(sub_seed(seed, 1)));
+    let hue = Perlin::new(perlin_seed(sub_seed(seed, 2)));
+    let size = NEBULA_TILE as f64;
+
+    par_image(NEBULA_TILE, NEBULA_TILE, |x, y| {
+        let (u, v) = (x as f64 / size, y as f64 / size);
+        // a few octaves so there's wispy bits on the big shapes
+        let (mut density, mut amplitude, mut across) = (0., 1., 3.);
+        for _ in 0..4 {
+            density += tiling_noise(&shape, u * across, v * across, across, across) * amplitude;
+            amplitude *= 0.5;
+            across *= 2.;
+        }
+        let density = ((density * 0.5 + 0.5 - 0.45) / 0.4).clamp(0., 1.) as f32;
+        let mix = (tiling_noise(&hue, u * 2., v * 2., 2., 2.) * 0.5 + 0.5).clamp(0., 1.) as f32;
+        Color::new(
+            r0 + (r1 - r0) * mix,
+            g0 + (g1 - g0) * mix,
+            b0 + (b1 - b0) * mix,
+            density * density * thickness,
+        ).into()
+    })
+}
@@ -0,0 +1,287 @@
+// Headless simulation. Generates one star system from a seed, steps
+// it a fixed amount of time per step (no window, no GPU, no wall
+// clock) and dumps diagnostics to a CSV every so often.
+//
+//   para-genesis-sim --seed 42 --steps 100000 --out stats.csv
+//
+// Same seed + same flags = same numbers, so it's usable for
+// experiments and CI.
+//
+// `--textures` also paints every body's textures (CPU side only, no
+// GPU needed) and says how long that took, for timing startup.
+// `--serial` does gravity and the body updates one at a time, the
+// way it used to be. The CSV should come out byte for byte the same.
+// With `--textures` it paints them one at a time on one thread too,
+// so the two timings can be put side by side.
+use std::fs::File;
+use std::future::Future;
+use std::io::{BufWriter, Write};
+use std::pin::pin;
+use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+use std::collections::HashSet;
+use std::time::Instant;
+
+use macroquad::texture::Image;
+
+use para_genesis_1::mathtools::*;
+use para_genesis_1::physics::*;
+use para_genesis_1::star::*;
+use para_genesis_1::rockybody::*;
+use para_genesis_1::star_system::*;
+use para_genesis_1::loading::*;
+use para_genesis_1::render::*;
+
+const USAGE: &str = "usage: para-genesis-sim [--seed <n|text>] [--steps <n>] [--dt <seconds>] [--every <n>] [--out <file.csv>] [--textures] [--serial]";
+
+struct Args {
+    seed: u64,
+    steps: u64,
+    dt: f32,
+    // write a row every this many steps
+    every: u64,
+    out: String,
+    textures: bool,
+    serial: bool,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        seed: 0,
+        steps: 10000,
+        dt: 1. / 60.,
+        every: 100,
+        out: String::from("stats.csv"),
+        textures: false,
+        serial: false,
+    };
+    let mut it = std::env::args().skip(1);
+    while let Some(flag) = it.next() {
+        if flag == "--help" || flag == "-h" {
+            return Err(String::from(USAGE));
+        }
+        if flag == "--textures" {
+            args.textures = true;
+            continue;
+        }
+        if flag == "--serial" {
+            args.serial = true;
+            continue;
+        }
+        let value = it.next().ok_or_else(|| format!("{} needs a value\n{}", flag, USAGE))?;
+        let bad = || format!("bad value `{}` for {}", value, flag);
+        match flag.as_str() {
+            "--seed" => args.seed = seed_from_text(&value),
+            "--steps" => args.steps = value.parse().map_err(|_| bad())?,
+            "--dt" => args.dt = value.parse().map_err(|_| bad())?,
+            "--every" => args.every = value.parse::<u64>().map_err(|_| bad())?.max(1),
+            "--out" => args.out = value.clone(),
+            _ => return Err(format!("unknown flag {}\n{}", flag, USAGE)),
+        }
+    }
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        },
+    };
+
+    let started = Instant::now();
+    let mut loading = Loading::silent("generating");
+    let mut system = block_on(StarSystem::new_rand(args.seed, &mut loading));
+    println!("INFO: seed {} generated {} bodies in {:.2?}", args.seed, system.bodies.len(), started.elapsed());
+    if args.textures {
+        paint_textures(&system, args.serial);
+    }
+
+    let file = match File::create(&args.out) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("ERROR: couldn't create {}: {}", args.out, e);
+            std::process::exit(1);
+        },
+    };
+    let mut out = BufWriter::new(file);
+    let written = writeln!(out, "{}", CSV_HEADER)
+        .and_then(|_| writeln!(out, "{}", csv_row(0, 0., &system)));
+    if let Err(e) = written {
+        eprintln!("ERROR: couldn't write {}: {}", args.out, e);
+        std::process::exit(1);
+    }
+
+    let started = Instant::now();
+    for step in 1..=args.steps {
+        if args.serial {
+            simulate_serial(&mut system, args.dt);
+        } else {
+            system.simulate(args.dt);
+        }
+        if step % args.every == 0 || step == args.steps {
+            if let Err(e) = writeln!(out, "{}", csv_row(step, step as f64 * args.dt as f64, &system)) {
+                eprintln!("ERROR: couldn't write {}: {}", args.out, e);
+                std::process::exit(1);
+            }
+        }
+        if step % (args.steps / 10).max(1) == 0 {
+            println!("INFO: step {}/{} ({} bodies)", step, args.steps, system.bodies.len());
+        }
+    }
+    if let Err(e) = out.flush() {
+        eprintln!("ERROR: couldn't write {}: {}", args.out, e);
+        std::process::exit(1);
+    }
+    println!("INFO: {} steps in {:.2?}, stats in {}", args.steps, started.elapsed(), args.out);
+}
+
+// `StarSystem::simulate` without any of the parallel passes
+fn simulate_serial(system: &mut StarSystem, dt: f32) {
+    update_gravity_physics_serial(&mut system.bodies);
+    check_collisions(&mut system.bodies);
+    for body in system.bodies.iter_mut() {
+        body.step(dt);
+    }
+}
+
+// what the game does behind the "Painting surfaces" loading screen,
+// minus uploading to the GPU. `serial` does it the old way, a body
+// at a time and a pixel at a time
+fn paint_textures(system: &StarSystem, serial: bool) {
+    let keys: Vec<TextureKey> = system.bodies.iter()
+        .filter_map(|body| body.appearance())
+        .map(|appearance| appearance.key())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let threads = if serial { 1 } else { rayon::current_num_threads() };
+    let pool = match rayon::ThreadPoolBuilder::new().num_threads(threads).build() {
+        Ok(pool) => pool,
+        Err(e) => {
+            eprintln!("ERROR: couldn't start {} texture threads: {}", threads, e);
+            std::process::exit(1);
+        },
+    };
+    let started = Instant::now();
+    let images: Vec<Vec<Image>> = if serial {
+        pool.install(|| keys.iter().map(|key| key.images()).collect())
+    } else {
+        pool.install(|| generate_images(&keys))
+    };
+    println!(
+        "INFO: painted {} textures ({} frames) for {} bodies in {:.2?} on {} threads",
+        keys.len(),
+        images.iter().map(|frames| frames.len()).sum::<usize>(),
+        system.bodies.len(),
+        started.elapsed(),
+        threads,
+    );
+}
+
+const CSV_HEADER: &str = "step,time,bodies,stars,rocky,total_mass,kinetic_energy,momentum_x,momentum_y,com_x,com_y,max_speed,farthest";
+
+fn csv_row(step: u64, time: f64, system: &StarSystem) -> String {
+    let (mut stars, mut rocky) = (0, 0);
+    let mut total_mass: f64 = 0.;
+    let mut kinetic: f64 = 0.;
+    let (mut px, mut py) = (0f64, 0f64);
+    let (mut mx, mut my) = (0f64, 0f64);
+    let mut max_speed: f32 = 0.;
+    let mut farthest: f32 = 0.;
+    for body in system.bodies.iter() {
+        if body.as_any().downcast_ref::<Star>().is_some() {
+            stars += 1;
+        } else if body.as_any().downcast_ref::<RockyBody>().is_some() {
+            rocky += 1;
+        }
+        let m = body.mass() as f64;
+        let (vx, vy) = (body.xvel() as f64, body.yvel() as f64);
+        total_mass += m;
+        kinetic += 0.5 * m * (vx * vx + vy * vy);
+        px += m * vx;
+        py += m * vy;
+        mx += m * body.xpos() as f64;
+        my += m * body.ypos() as f64;
+        max_speed = max_speed.max((body.xvel() * body.xvel() + body.yvel() * body.yvel()).sqrt());
+        let (dx, dy) = (body.xpos() - system.origin.0, body.ypos() - system.origin.1);
+        farthest = farthest.max((dx * dx + dy * dy).sqrt());
+    }
+    let (com_x, com_y) = if total_mass > 0. { (mx / total_mass, my / total_mass) } else { (0., 0.) };
+    format!(
+        "{},{},{},{},{},{:e},{:e},{:e},{:e},{},{},{},{}",
+        step, time, system.bodies.len(), stars, rocky, total_mass, kinetic, px, py, com_x, com_y, max_speed, farthest,
+    )
+}
+
+// Generation is `async` because in the game it yields to the
+// loading screen. A silent `Loading` never does, so it's done the
+// first time it's polled. Anything that does wait (a loading screen
+// that draws) would be waiting on a window that isn't there, so
+// that's a bug rather than something to spin on.
+fn block_on<F: Future>(future: F) -> F::Output {
+    fn noop_raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker { noop_raw_waker() }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(std::ptr::null(), &VTABLE)
+    }
+    let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+    match pin!(future).poll(&mut cx) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("headless generation tried to wait for a frame (use Loading::silent)"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(seed: u64, steps: u64) -> Vec<String> {
+        let mut system = block_on(StarSystem::new_rand(seed, &mut Loading::silent("test")));
+        let mut rows = vec![csv_row(0, 0., &system)];
+        for step in 1..=steps {
+            system.simulate(1. / 60.);
+            rows.push(csv_row(step, step as f64 / 60., &system));
+        }
+        rows
+    }
+
+    // what a body is and where, down to the bit
+    fn fingerprint(body: &dyn PhysObj) -> (&'static str, [u32; 5], u64, Option<u64>) {
+        let any = body.as_any();
+        let (kind, seed) = if let Some(star) = any.downcast_ref::<Star>() {
+            ("star", Some(star.seed()))
+        } else if let Some(rocky) = any.downcast_ref::<RockyBody>() {
+            ("rocky", Some(rocky.seed()))
+        } else {
+            ("other", None)
+        };
+        let floats = [body.xpos(), body.ypos(), body.xvel(), body.yvel(), body.radius()].map(f32::to_bits);
+        (kind, floats, body.mass(), seed)
+    }
+
+    #[test]
+    fn same_seed_same_system() {
+        let generate = |seed| block_on(StarSystem::new_rand(seed, &mut Loading::silent("test")));
+        let (a, b) = (generate(42), generate(42));
+        assert_eq!(a.bodies.len(), b.bodies.len());
+        for (a, b) in a.bodies.iter().zip(b.bodies.iter()) {
+            assert_eq!(fingerprint(a.as_ref()), fingerprint(b.as_ref()));
+        }
+        assert_eq!((a.mass, a.radius.to_bits(), a.origin), (b.mass, b.radius.to_bits(), b.origin));
+        assert_eq!((a.life.seed, a.life.home_seed), (b.life.seed, b.life.home_seed));
+
+        let c = generate(43);
+        assert!(a.bodies.iter().zip(c.bodies.iter()).any(|(a, c)| fingerprint(a.as_ref()) != fingerprint(c.as_ref())));
+    }
+
+    #[test]
+    fn same_seed_same_stats() {
+        let first = run(42, 5);
+        assert_eq!(first, run(42, 5));
+        assert_ne!(first[0], run(43, 0)[0]);
+    }
+}
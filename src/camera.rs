@@ -1,25 +1,404 @@
+use macroquad::prelude::*;
+use super::physics::*;
+
+// zoom is screen pixels per world unit. MIN_ZOOM fits a whole
+// star system on screen, MAX_ZOOM is nose-against-the-glass.
+pub const MIN_ZOOM: f64 = 0.005;
+pub const MAX_ZOOM: f64 = 4.;
+// every wheel notch / key press multiplies the zoom by this,
+// so zooming feels the same at every scale
+const ZOOM_STEP: f64 = 1.2;
+// how quickly `zoom` catches up to `target_zoom` (per second)
+const ZOOM_SPEED: f64 = 12.;
+// how quickly the camera catches up to whatever it's looking at
+const CAMERA_SMOOTHING: f32 = 8.;
+// free pan speed for the arrow keys, in screen pixels per second
+const PAN_SPEED: f32 = 900.;
+
+pub const CAMERA_MODE_KEY: KeyCode = KeyCode::C;
+
+// bodies less than this many pixels across aren't worth a
+// texture, they just get drawn as a dot
+pub const LOD_PIXELS: f32 = 6.;
+
+// how many bodies made it to the screen last frame, for the debug overlay
+#[derive(Clone, Copy, Default)]
+pub struct RenderStats {
+    pub drawn: usize,
+    pub culled: usize,
+    // drawn, but as a dot (see `LOD_PIXELS`)
+    pub lod: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraMode {
+    // sit on the player (the first body)
+    Follow,
+    // go wherever the arrow keys / right mouse drag say
+    FreePan,
+    // sit on `locked_body`. Falls back to following the
+    // player if nothing is selected
+    LockBody,
+    // zoom out and center so every body fits on screen
+    FrameAll,
+}
+
+impl CameraMode {
+    pub const ALL: [CameraMode; 4] = [CameraMode::Follow, CameraMode::FreePan, CameraMode::LockBody, CameraMode::FrameAll];
+
+    pub fn next(&self) -> CameraMode {
+        match self {
+            CameraMode::Follow => CameraMode::FreePan,
+            CameraMode::FreePan => CameraMode::LockBody,
+            CameraMode::LockBody => CameraMode::FrameAll,
+            CameraMode::FrameAll => CameraMode::Follow,
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            CameraMode::Follow => "follow",
+            CameraMode::FreePan => "free",
+            CameraMode::LockBody => "lock",
+            CameraMode::FrameAll => "frame_all",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<CameraMode> {
+        match name {
+            "follow" => Some(CameraMode::Follow),
+            "free" => Some(CameraMode::FreePan),
+            "lock" => Some(CameraMode::LockBody),
+            "frame_all" => Some(CameraMode::FrameAll),
+            _ => None,
+        }
+    }
+}
+
 pub struct ZCamera {
+    // world position of the top left corner of the screen
     pub xpos: f32,
-    pub ypos: f32, 
+    pub ypos: f32,
     pub zoom: f64,
+    pub target_zoom: f64,
+    // screen point the current zoom is happening around.
+    // whatever is under it stays put while zooming.
+    zoom_anchor: (f32, f32),
+    // how far the followed body is from the middle of the
+    // screen (world units). Zooming towards the cursor moves
+    // the view, and this is what lets the follow/lock modes
+    // keep that instead of snapping back.
+    pub follow_offset: (f32, f32),
+    pub mode: CameraMode,
+    // which of the system's bodies, for `CameraMode::LockBody`
+    pub locked_body: Option<BodyId>,
+    last_drag: Option<(f32, f32)>,
 }
 
 impl ZCamera {
     pub fn new_origin() -> ZCamera {
         ZCamera {
-            xpos: 0., 
+            xpos: 0.,
             ypos: 0.,
             zoom: 0.1,
+            target_zoom: 0.1,
+            zoom_anchor: (0., 0.),
+            follow_offset: (0., 0.),
+            mode: CameraMode::Follow,
+            locked_body: None,
+            last_drag: None,
+        }
+    }
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode != self.mode {
+            // start the new mode looking right at its subject,
+            // the smoothing in `update` does the easing over
+            self.follow_offset = (0., 0.);
+            self.mode = mode;
         }
     }
-    pub fn add_zoom(&mut self, dz: f32) {
-        self.zoom += dz as f64 / 2.;
+    pub fn center(&self) -> (f32, f32) {
+        self.screen_to_world(screen_width() / 2., screen_height() / 2.)
+    }
+    pub fn set_center(&mut self, x: f32, y: f32) {
+        self.xpos = x - (screen_width() as f64 / 2. / self.zoom) as f32;
+        self.ypos = y - (screen_height() as f64 / 2. / self.zoom) as f32;
+    }
+    // Everything that goes between world coords and screen pixels
+    // should go through these so nothing ends up with its own
+    // slightly different idea of where things are.
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            ((x as f64 - self.xpos as f64) * self.zoom) as f32,
+            ((y as f64 - self.ypos as f64) * self.zoom) as f32,
+        )
+    }
+    pub fn screen_to_world(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
+        (
+            (self.xpos as f64 + screen_x as f64 / self.zoom) as f32,
+            (self.ypos as f64 + screen_y as f64 / self.zoom) as f32,
+        )
+    }
+    // world lengths -> pixels
+    pub fn scale(&self, len: f32) -> f32 {
+        (len as f64 * self.zoom) as f32
+    }
+    // the part of the world that's on screen right now
+    pub fn visible_rect(&self) -> Rect {
+        let (x1, y1) = self.screen_to_world(screen_width(), screen_height());
+        Rect::new(self.xpos, self.ypos, x1 - self.xpos, y1 - self.ypos)
+    }
+    // whether a circle at world (x, y) with this radius touches the screen at all
+    pub fn is_visible(&self, x: f32, y: f32, radius: f32) -> bool {
+        let view = self.visible_rect();
+        x + radius >= view.x
+            && y + radius >= view.y
+            && x - radius <= view.x + view.w
+            && y - radius <= view.y + view.h
+    }
+    // Draws `texture` centered on world (x, y), `world_size` world
+    // units across. Body textures are made at one texel per world
+    // unit, so for those that's just the texture's own size.
+    pub fn draw_world_texture(&self, texture: &Texture2D, x: f32, y: f32, world_size: Vec2) {
+        let (sx, sy) = self.world_to_screen(x - world_size.x / 2., y - world_size.y / 2.);
+        draw_texture_ex(
+            texture,
+            sx,
+            sy,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(self.scale(world_size.x), self.scale(world_size.y))),
+                ..Default::default()
+            }
+        );
+    }
+    // what small bodies get drawn as instead (see `use_lod`)
+    pub fn draw_world_dot(&self, x: f32, y: f32, radius: f32, min_pixels: f32, color: Color) {
+        let (sx, sy) = self.world_to_screen(x, y);
+        draw_circle(sx, sy, self.scale(radius).max(min_pixels), color);
+    }
+    // whether something this big should be drawn as a dot instead of a texture
+    pub fn use_lod(&self, radius: f32) -> bool {
+        radius * 2. * (self.zoom as f32) < LOD_PIXELS
+    }
+    // `steps` is in zoom steps (one wheel notch = 1), positive zooms in
+    pub fn add_zoom(&mut self, steps: f32) {
+        let (half_w, half_h) = (screen_width() / 2., screen_height() / 2.);
+        self.zoom_towards(steps, half_w, half_h);
+    }
+    pub fn zoom_towards(&mut self, steps: f32, screen_x: f32, screen_y: f32) {
+        self.target_zoom = (self.target_zoom * ZOOM_STEP.powf(steps as f64)).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom_anchor = (screen_x, screen_y);
+    }
+    // `mouse` / `keyboard` are false when a window already used them
+    pub fn handle_input(&mut self, mouse: bool, keyboard: bool) {
+        if keyboard && is_key_pressed(CAMERA_MODE_KEY) {
+            self.set_mode(self.mode.next());
+        }
+
+        let (_, wheel) = mouse_wheel();
+        if mouse && wheel != 0. {
+            // frame-all picks its own zoom, so zooming by hand means
+            // you want to look around yourself
+            if self.mode == CameraMode::FrameAll {
+                self.set_mode(CameraMode::FreePan);
+            }
+            let (mouse_x, mouse_y) = mouse_position();
+            self.zoom_towards(wheel.signum(), mouse_x, mouse_y);
+        }
+        if keyboard && (is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd)) {
+            self.add_zoom(1.);
+        }
+        if keyboard && (is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract)) {
+            self.add_zoom(-1.);
+        }
+
+        // panning by hand from any mode drops you into free pan
+        let dt = get_frame_time();
+        let (mut pan_x, mut pan_y) = (0., 0.);
+        if keyboard {
+            if is_key_down(KeyCode::Left) { pan_x -= PAN_SPEED * dt; }
+            if is_key_down(KeyCode::Right) { pan_x += PAN_SPEED * dt; }
+            if is_key_down(KeyCode::Up) { pan_y -= PAN_SPEED * dt; }
+            if is_key_down(KeyCode::Down) { pan_y += PAN_SPEED * dt; }
+        }
+        if mouse && is_mouse_button_down(MouseButton::Right) {
+            let (mouse_x, mouse_y) = mouse_position();
+            if let Some((last_x, last_y)) = self.last_drag {
+                pan_x -= mouse_x - last_x;
+                pan_y -= mouse_y - last_y;
+            }
+            self.last_drag = Some((mouse_x, mouse_y));
+        } else {
+            self.last_drag = None;
+        }
+        if pan_x != 0. || pan_y != 0. {
+            self.set_mode(CameraMode::FreePan);
+            self.xpos += pan_x / self.zoom as f32;
+            self.ypos += pan_y / self.zoom as f32;
+        }
+    }
+
+    // Moves the camera for whatever mode it's in. `bodies` is the
+    // active system, with the player (if it's here) up front.
+    pub fn update(&mut self, bodies: &[Box<dyn PhysObj>], dt: f32) {
+        self.update_zoom(dt);
+
+        let subject = match self.mode {
+            CameraMode::Follow => bodies.first(),
+            CameraMode::LockBody => self.locked_body
+                .and_then(|id| body_index(bodies, id))
+                .and_then(|i| bodies.get(i))
+                .or(bodies.first()),
+            CameraMode::FrameAll => {
+                self.frame_all(bodies, dt);
+                return;
+            },
+            CameraMode::FreePan => return,
+        };
+        let body = match subject {
+            Some(body) => body,
+            None => return,
+        };
+
+        // zooming towards the cursor pushes the body off center
+        // (see `update_zoom`), just don't let it leave the screen
+        let max_dx = screen_width() / 2. / self.zoom as f32 * 0.9;
+        let max_dy = screen_height() / 2. / self.zoom as f32 * 0.9;
+        self.follow_offset.0 = self.follow_offset.0.clamp(-max_dx, max_dx);
+        self.follow_offset.1 = self.follow_offset.1.clamp(-max_dy, max_dy);
+
+        let desired = (body.xpos() + self.follow_offset.0, body.ypos() + self.follow_offset.1);
+        self.ease_center_towards(desired, dt);
+    }
+
+    fn frame_all(&mut self, bodies: &[Box<dyn PhysObj>], dt: f32) {
+        if bodies.is_empty() {
+            return;
+        }
+        let (mut min_x, mut min_y) = (f32::MAX, f32::MAX);
+        let (mut max_x, mut max_y) = (f32::MIN, f32::MIN);
+        for body in bodies.iter() {
+            min_x = min_x.min(body.xpos() - body.radius());
+            min_y = min_y.min(body.ypos() - body.radius());
+            max_x = max_x.max(body.xpos() + body.radius());
+            max_y = max_y.max(body.ypos() + body.radius());
+        }
+        let fit_x = screen_width() as f64 / ((max_x - min_x) as f64 * 1.1).max(1.);
+        let fit_y = screen_height() as f64 / ((max_y - min_y) as f64 * 1.1).max(1.);
+        self.target_zoom = fit_x.min(fit_y).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.zoom_anchor = (screen_width() / 2., screen_height() / 2.);
+        self.ease_center_towards(((min_x + max_x) / 2., (min_y + max_y) / 2.), dt);
+    }
+
+    fn ease_center_towards(&mut self, desired: (f32, f32), dt: f32) {
+        let t = 1. - (-CAMERA_SMOOTHING * dt).exp();
+        let (cx, cy) = self.center();
+        self.set_center(cx + (desired.0 - cx) * t, cy + (desired.1 - cy) * t);
+    }
+    // Eases `zoom` towards `target_zoom` (in log space, so it's
+    // just as smooth zoomed way in as way out), keeping the
+    // world point under the zoom anchor fixed on screen.
+    pub fn update_zoom(&mut self, dt: f32) {
+        if self.zoom == self.target_zoom {
+            return;
+        }
+        let t = (ZOOM_SPEED * dt as f64).min(1.);
+        let mut new_zoom = (self.zoom.ln() + (self.target_zoom.ln() - self.zoom.ln()) * t).exp();
+        if (new_zoom / self.target_zoom - 1.).abs() < 0.001 {
+            new_zoom = self.target_zoom;
+        }
+
+        let (ax, ay) = self.zoom_anchor;
+        let (half_w, half_h) = (screen_width() / 2., screen_height() / 2.);
+        // world point under the anchor before and after, solved for
+        // the new corner so it lines up again
+        let (world_x, world_y) = self.screen_to_world(ax, ay);
+        let new_xpos = (world_x as f64 - ax as f64 / new_zoom) as f32;
+        let new_ypos = (world_y as f64 - ay as f64 / new_zoom) as f32;
+
+        // same thing for the middle of the screen, that shift is
+        // what the followed body has to be offset by
+        let old_cx = self.xpos + (half_w as f64 / self.zoom) as f32;
+        let old_cy = self.ypos + (half_h as f64 / self.zoom) as f32;
+        let new_cx = new_xpos + (half_w as f64 / new_zoom) as f32;
+        let new_cy = new_ypos + (half_h as f64 / new_zoom) as f32;
+        self.follow_offset.0 += new_cx - old_cx;
+        self.follow_offset.1 += new_cy - old_cy;
+
+        self.xpos = new_xpos;
+        self.ypos = new_ypos;
+        self.zoom = new_zoom;
     }
     pub fn clone(&self) -> ZCamera {
         ZCamera {
             xpos: self.xpos,
             ypos: self.ypos,
             zoom: self.zoom,
+            target_zoom: self.target_zoom,
+            zoom_anchor: self.zoom_anchor,
+            follow_offset: self.follow_offset,
+            mode: self.mode,
+            locked_body: self.locked_body,
+            last_drag: self.last_drag,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ZOOMS: [f64; 4] = [0.01, 0.1, 1., 10.];
+
+    fn camera_at(xpos: f32, ypos: f32, zoom: f64) -> ZCamera {
+        let mut camera = ZCamera::new_origin();
+        camera.xpos = xpos;
+        camera.ypos = ypos;
+        camera.zoom = zoom;
+        camera.target_zoom = zoom;
+        camera
+    }
+
+    // f32 only has so many digits, allow for the size of the numbers
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() <= 1e-3 + b.abs() * 1e-5
+    }
+
+    #[test]
+    fn world_round_trips_through_screen() {
+        let points = [(0., 0.), (12400., -3250.5), (-98000., 45000.), (1.25, 0.75)];
+        for zoom in ZOOMS {
+            let camera = camera_at(12345.6, -789.25, zoom);
+            for (x, y) in points {
+                let (sx, sy) = camera.world_to_screen(x, y);
+                let (wx, wy) = camera.screen_to_world(sx, sy);
+                assert!(close(wx, x) && close(wy, y), "zoom {}: ({}, {}) came back as ({}, {})", zoom, x, y, wx, wy);
+            }
+        }
+    }
+
+    #[test]
+    fn screen_round_trips_through_world() {
+        let points = [(0., 0.), (640., 360.), (1919., 1079.), (-50., 2000.)];
+        for zoom in ZOOMS {
+            let camera = camera_at(-4500., 820.5, zoom);
+            for (x, y) in points {
+                let (wx, wy) = camera.screen_to_world(x, y);
+                let (sx, sy) = camera.world_to_screen(wx, wy);
+                assert!(close(sx, x) && close(sy, y), "zoom {}: ({}, {}) came back as ({}, {})", zoom, x, y, sx, sy);
+            }
+        }
+    }
+
+    #[test]
+    fn corner_and_lengths_line_up() {
+        for zoom in ZOOMS {
+            let camera = camera_at(300., -200., zoom);
+            let (sx, sy) = camera.world_to_screen(300., -200.);
+            assert!(close(sx, 0.) && close(sy, 0.));
+            // a world length comes out the same size wherever it is
+            let (ax, _) = camera.world_to_screen(1000., 0.);
+            let (bx, _) = camera.world_to_screen(1250., 0.);
+            assert!(close(bx - ax, camera.scale(250.)));
         }
     }
 }
@@ -0,0 +1,287 @@
+use macroquad::prelude::*;
+use std::time::{Instant, Duration};
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
+
+use super::physics::*;
+use super::star_system::*;
+use super::player::*;
+use super::mathtools::*;
+use super::loading::*;
+use super::render::*;
+
+// how many star systems make up the galaxy, and how big
+// the galaxy is (in galactic units, NOT system pixels)
+const N_SYSTEMS: usize = 300;
+const GALAXY_RADIUS: f32 = 1000.;
+
+// systems the player isn't in only get stepped this often.
+// Bodies integrate over however long it's been since their
+// last update, so this just makes them take bigger steps.
+const COARSE_STEP: Duration = Duration::from_millis(750);
+
+// only look for a destination within this angle of where
+// the player is heading when they leave a system
+const JUMP_CONE: f32 = std::f32::consts::PI / 4.;
+
+// One dot on the galaxy map. The actual `StarSystem` is only
+// generated the first time somebody goes there.
+pub struct SystemEntry {
+    pub xpos: f32,
+    pub ypos: f32,
+    pub seed: u64,
+    pub name: String,
+    pub system: Option<StarSystem>,
+}
+
+pub struct Galaxy {
+    pub seed: u64,
+    pub entries: Vec<SystemEntry>,
+    pub active: usize,
+    // set from the galaxy map. If there is one, flying off the
+    // edge of the system takes you there instead of wherever
+    // you happen to be pointed.
+    pub destination: Option<usize>,
+    // seconds of simulation so far (pauses and loading screens
+    // don't count)
+    pub sim_time: f64,
+    last_coarse_update: Instant,
+}
+
+impl Galaxy {
+    // Just the layout. Nothing is playable until `start` has
+    // generated the first system.
+    pub fn new(seed: u64) -> Galaxy {
+        Galaxy {
+            seed,
+            entries: layout_galaxy(seed),
+            active: 0,
+            destination: None,
+            sim_time: 0.,
+            last_coarse_update: Instant::now(),
+        }
+    }
+
+    // Generates the starting system (the slow part, hence the
+    // loading screen) and drops the player in it.
+    pub async fn start(&mut self, player: Player, loading: &mut Loading) {
+        let mut system = generate_system(&self.entries[self.active], loading).await;
+        let (x, y) = (player.xpos(), player.ypos());
+        system.place_player(player, x, y);
+        self.entries[self.active].system = Some(system);
+        self.reset_clocks();
+    }
+
+    // Bodies step by however long it's been since they last moved,
+    // so after a pause (or a long load) everything has to be told
+    // to pick up from now rather than make up the lost time.
+    pub fn reset_clocks(&mut self) {
+        for entry in self.entries.iter_mut() {
+            if let Some(system) = entry.system.as_mut() {
+                system.reset_clock();
+            }
+        }
+        self.last_coarse_update = Instant::now();
+    }
+
+    pub fn active_system(&self) -> &StarSystem {
+        self.entries[self.active].system.as_ref().expect("active system was never generated")
+    }
+
+    pub fn active_system_mut(&mut self) -> &mut StarSystem {
+        self.entries[self.active].system.as_mut().expect("active system was never generated")
+    }
+
+    pub fn active_entry(&self) -> &SystemEntry {
+        &self.entries[self.active]
+    }
+
+    pub async fn update(&mut self) {
+        self.sim_time += get_frame_time() as f64;
+        {
+            let system = self.active_system_mut();
+            update_gravity_physics(&mut system.bodies);
+            check_collisions(&mut system.bodies);
+            system.update();
+        }
+
+        let now = Instant::now();
+        if now.duration_since(self.last_coarse_update) >= COARSE_STEP {
+            self.last_coarse_update = now;
+            let active = self.active;
+            for (i, entry) in self.entries.iter_mut().enumerate() {
+                if i == active {
+                    continue;
+                }
+                if let Some(system) = entry.system.as_mut() {
+                    update_gravity_physics(&mut system.bodies);
+                    check_collisions(&mut system.bodies);
+                    // the player's never in these, so no keyboard
+                    update_bodies(&mut system.bodies);
+                }
+            }
+        }
+
+        if self.active_system().player_outside_edge() {
+            if let Some(destination) = self.pick_destination() {
+                self.travel_to(destination).await;
+            }
+        }
+    }
+
+    pub fn draw(&mut self, render: &mut RenderCache) {
+        let time = self.sim_time;
+        self.active_system_mut().draw(render, time);
+    }
+
+    // Which system the player ends up in when they fly off the edge
+    // of the current one: the destination picked on the map if there
+    // is one, otherwise the closest system roughly in the direction
+    // they're going, or just the closest one if nothing lines up.
+    pub fn pick_destination(&self) -> Option<usize> {
+        if let Some(destination) = self.destination {
+            if destination != self.active {
+                return Some(destination);
+            }
+        }
+        let system = self.active_system();
+        let player = system.player()?;
+        let (mut hx, mut hy) = (player.xvel(), player.yvel());
+        if hx == 0. && hy == 0. {
+            hx = player.xpos() - system.origin.0;
+            hy = player.ypos() - system.origin.1;
+        }
+        let heading = f32::atan2(hy, hx);
+
+        let here = &self.entries[self.active];
+        let mut in_cone: Option<(usize, f32)> = None;
+        let mut nearest: Option<(usize, f32)> = None;
+        for (i, entry) in self.entries.iter().enumerate() {
+            if i == self.active {
+                continue;
+            }
+            let (dx, dy) = (entry.xpos - here.xpos, entry.ypos - here.ypos);
+            let d = (dx * dx + dy * dy).sqrt();
+            let mut off_angle = (f32::atan2(dy, dx) - heading).abs();
+            if off_angle > std::f32::consts::PI {
+                off_angle = 2. * std::f32::consts::PI - off_angle;
+            }
+            if off_angle <= JUMP_CONE && in_cone.is_none_or(|(_, best)| d < best) {
+                in_cone = Some((i, d));
+            }
+            if nearest.is_none_or(|(_, best)| d < best) {
+                nearest = Some((i, d));
+            }
+        }
+        in_cone.or(nearest).map(|(i, _)| i)
+    }
+
+    // Moves the player to `destination`, generating it first if nobody
+    // has been there yet. The player shows up on the far edge of the
+    // new system, still flying the same way it left the old one.
+    pub async fn travel_to(&mut self, destination: usize) {
+        if destination == self.active {
+            return;
+        }
+        if self.entries[destination].system.is_none() {
+            let mut loading = Loading::new(&format!("Entering {}", self.entries[destination].name));
+            loading.show().await;
+            let system = generate_system(&self.entries[destination], &mut loading).await;
+            self.entries[destination].system = Some(system);
+            self.reset_clocks();
+        }
+
+        let (from_entry, to_entry) = two_entries_mut(&mut self.entries, self.active, destination);
+        let from = from_entry.system.as_mut().expect("active system was never generated");
+        let to = to_entry.system.as_mut().expect("destination was just generated");
+
+        let (dx, dy) = (to_entry.xpos - from_entry.xpos, to_entry.ypos - from_entry.ypos);
+        let d = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+        let (ox, oy) = to.origin;
+        let arrival_x = ox - dx / d * to.radius * 0.9;
+        let arrival_y = oy - dy / d * to.radius * 0.9;
+
+        StarSystem::sync_player(from, to, arrival_x, arrival_y);
+        // keep looking at things the same way after the jump
+        to.camera = from.camera.clone();
+        to.camera.locked_body = None;
+        println!("INFO: arrived at {}", to_entry.name);
+        self.active = destination;
+        if self.destination == Some(destination) {
+            self.destination = None;
+        }
+    }
+}
+
+fn two_entries_mut(entries: &mut [SystemEntry], a: usize, b: usize) -> (&mut SystemEntry, &mut SystemEntry) {
+    if a < b {
+        let (left, right) = entries.split_at_mut(b);
+        (&mut left[a], &mut right[0])
+    } else {
+        let (left, right) = entries.split_at_mut(a);
+        (&mut right[0], &mut left[b])
+    }
+}
+
+async fn generate_system(entry: &SystemEntry, loading: &mut Loading) -> StarSystem {
+    let mut system = StarSystem::new_rand(entry.seed, loading).await;
+    system.xpos = entry.xpos;
+    system.ypos = entry.ypos;
+    system
+}
+
+// Lays out where every system in the galaxy is (no generation
+// happens here, that's all lazy). Systems are scattered over a
+// disc, bunched up towards the middle.
+pub fn layout_galaxy(seed: u64) -> Vec<SystemEntry> {
+    let mut rng = StdRng::seed_from_u64(sub_seed(seed, 0));
+    let mut entries = Vec::with_capacity(N_SYSTEMS);
+    for i in 0..N_SYSTEMS {
+        let r = GALAXY_RADIUS * rng.gen_range(0.0f32..1.0).powf(0.75);
+        let theta = rng.gen_range(0.0f32..std::f32::consts::TAU);
+        let system_seed = sub_seed(seed, i as u64 + 1);
+        entries.push(SystemEntry {
+            xpos: r * theta.cos(),
+            ypos: r * theta.sin(),
+            seed: system_seed,
+            name: system_name(system_seed),
+            system: None,
+        });
+    }
+    entries
+}
+
+pub fn system_name(seed: u64) -> String {
+    let syllables = [
+        "ka", "ro", "zen", "tu", "mi", "sol", "ver", "an", "qua", "lis",
+        "dor", "eth", "ix", "o", "pra", "nul", "sha", "ter", "vo", "ym",
+    ];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let n_syllables = rng.gen_range(2..4);
+    let mut name = String::new();
+    for _ in 0..n_syllables {
+        name.push_str(syllables[rng.gen_range(0..syllables.len())]);
+    }
+    let mut chars = name.chars();
+    let first = chars.next().unwrap().to_ascii_uppercase();
+    format!("{}{}-{}", first, chars.as_str(), rng.gen_range(1..100))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(entries: &[SystemEntry]) -> Vec<(u32, u32, u64, String)> {
+        entries.iter()
+            .map(|entry| (entry.xpos.to_bits(), entry.ypos.to_bits(), entry.seed, entry.name.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_same_layout() {
+        let layout = fingerprint(&layout_galaxy(42));
+        assert_eq!(layout.len(), N_SYSTEMS);
+        assert_eq!(layout, fingerprint(&layout_galaxy(42)));
+        assert_ne!(layout, fingerprint(&layout_galaxy(43)));
+    }
+}
@@ -1,123 +1,494 @@
 use macroquad::prelude::*;
 use macroquad::audio::*;
-use crate::menu::VintageWindow;
+use std::time::SystemTime;
 
 use super::camera::*;
 use super::star_system::*;
+use super::galaxy::*;
+use super::map::*;
+use super::settings::*;
+use super::inspector::*;
 use super::rockybody::*;
 use super::menu::*;
 use super::player::*;
 use super::physics::*;
+use super::ui::*;
+use super::taskbar::*;
+use super::loading::*;
+use super::widgets::*;
+use super::save::*;
+use super::save_browser::*;
+use super::render::*;
+use super::background::*;
 
-// TODO WE SHALL CONSTRUCT A GAME WHICH HAS A GAMESTATE
+const QUIT_WINDOW: &str = "quit";
+const PAUSE_WINDOW: &str = "pause";
+
+// P pauses too, Escape does it as well but also backs out of things
+const PAUSE_KEY: KeyCode = KeyCode::P;
+
+// Everything about one playthrough: the universe, the UI over it,
+// and which state (playing, paused, looking at the map, ...) it's in.
+// States only change through `set_state`.
 pub struct Game {
     pub game_state: GameState,
-    universe: Vec<StarSystem>,
+    pub seed: u64,
+    universe: Galaxy,
+    // textures for whatever's in the active system
+    render: RenderCache,
+    // the sky behind the active system
+    background: Background,
     camera:  ZCamera,
     player: Player,
-    //TEMP - TODO make a trait for ui so we can
-    // do our signature fucked up 
-    // solution : `Vec<Box<dyn UI>>`
-    ui_elements: Vec<VintageWindow>,
-    quitting: Quitter,
+    ui: UiStack,
+    // where backing out of the quit dialog goes back to
+    quit_from: GameState,
+    map: SystemMap,
+    settings: Settings,
+    debug_overlay: bool,
+    inspector: Inspector,
+    // a save waiting to be restored on the next StartingUp,
+    // instead of generating the universe from the seed
+    pending_save: Option<SaveFile>,
+    saves: SaveBrowser,
+    // `universe.sim_time` at the last autosave (or load)
+    last_autosave: f64,
 }
 
 impl Game {
-    pub async fn new() -> Game {
+    pub async fn new(seed: u64) -> Game {
 
         set_pc_assets_folder("src");
         let mut limd_sound: &Sound = &load_sound("limd.wav").await.unwrap();
         set_sound_volume(limd_sound, 0.5);
         play_sound_once(limd_sound);
         let mut camera: ZCamera = ZCamera::new_origin();
-        let mut universe: Vec<StarSystem> = Vec::new();
         let mut player = Player::new();
-        universe.push(StarSystem::new_rand(player.clone()).await);
-        let mut ui_elements = Vec::new();
-        ui_elements.push(VintageWindow::new(
-            400, 200, String::from("You really tryna quit?"),
-            String::from("ERROR: QUITTER DETECTED"),
-            String::from("you done?"),
-            String::from("Yes"),
+        println!("INFO: universe seed {}", seed);
+        let universe = Galaxy::new(seed);
+        let mut settings = Settings::load();
+        settings.last_seed = Some(seed);
+        settings.save();
+        let mut ui = UiStack::new(vec![
+            (String::from("System map"), UiAction::ToggleMap),
+            (String::from("Debug overlay"), UiAction::ToggleDebugOverlay),
+            (String::from("Settings"), UiAction::ShowWindow(SETTINGS_WINDOW)),
+            (String::from("Pause"), UiAction::Pause),
+            (String::from("Quit..."), UiAction::ShowWindow(QUIT_WINDOW)),
+        ]);
+        ui.push(Box::new(VintageWindow::message(
+            QUIT_WINDOW,
+            "You really tryna quit?",
+            &["ERROR: QUITTER DETECTED", "    you done?"],
+            "Yes",
+            UiAction::Quit,
             WindowType::Error,
-            load_sound("lcuterror98.wav").await.unwrap(),
-        ));
+            Some(load_sound("lcuterror98.wav").await.unwrap()),
+        )));
+        ui.push(Box::new(pause_window()));
+        ui.push(Box::new(Inspector::window()));
+        ui.push(Box::new(settings_window(&settings)));
+        ui.push(Box::new(SaveBrowser::window()));
+        ui.restore_positions(&settings.window_positions);
         Game {
-            game_state: GameState::Playing,
+            game_state: GameState::StartingUp,
+            seed,
             universe,
+            render: RenderCache::new(),
+            background: Background::empty(),
             camera,
             player,
-            ui_elements,
-            quitting: Quitter::No,
+            ui,
+            quit_from: GameState::Playing,
+            map: SystemMap::new(),
+            settings,
+            debug_overlay: false,
+            inspector: Inspector::new(),
+            pending_save: None,
+            saves: SaveBrowser::new(),
+            last_autosave: 0.,
         }
     }
+
+    // Same as `new`, but the universe comes out of `save` once
+    // the game starts up
+    pub async fn from_save(save: SaveFile) -> Game {
+        let mut game = Game::new(save.seed).await;
+        game.pending_save = Some(save);
+        game
+    }
     pub async fn update(&mut self) {
-        match self.quitting {
-            Quitter::No => { },
-            Quitter::Maybe => { },
-            Quitter::Yes => { self.game_state = GameState::Quit },
-        }
         match self.game_state {
-            GameState::StartingUp => {},
-            GameState::Playing => {
-                for system in self.universe.iter_mut() {
-                    update_gravity_physics(&mut system.bodies);
-                    check_collisions(&mut system.bodies).await;
-                    system.update();
+            GameState::StartingUp => {
+                self.start_up().await;
+            },
+            GameState::Playing | GameState::Map => {
+                let system_before = self.universe.active;
+                self.universe.update().await;
+                if self.universe.active != system_before {
+                    // the targeted and selected body ids belong to the old system
+                    self.map.nav_target = None;
+                    self.inspector.selected = None;
+                    let name = self.universe.active_entry().name.clone();
+                    let mut loading = Loading::new(&format!("Entering {}...", name));
+                    loading.show().await;
+                    self.prepare_system(&mut loading).await;
+                }
+                self.autosave_if_due();
+                let ui = self.update_ui();
+                // the UI might have just changed the state
+                match self.game_state {
+                    GameState::Map if !ui.mouse => {
+                        self.map.update(&mut self.universe);
+                    },
+                    GameState::Playing => {
+                        if !ui.mouse {
+                            self.inspector.update(self.universe.active_system());
+                        }
+                        self.handle_camera_input(!ui.mouse, !ui.keyboard);
+                    },
+                    _ => {},
+                }
+                let name = self.universe.active_entry().name.clone();
+                self.inspector.refresh(&mut self.ui, self.universe.active_system(), &name);
+                if !ui.keyboard {
+                    self.handle_inputs();
+                }
+                if let Some(player) = self.universe.active_system().player() {
+                    self.player = player.clone();
                 }
             },
-            GameState::AboutToQuit => {
-                self.handle_quitter();
+            // everything is frozen, only the windows still work
+            GameState::Paused | GameState::ConfirmQuit => {
+                let ui = self.update_ui();
+                if !ui.keyboard {
+                    self.handle_inputs();
+                }
             },
-            GameState::Quit => { std::process::exit(0) },
+            // main.rs takes it from here
+            GameState::Menu | GameState::Quit => {},
         }
     }
     pub fn draw(&mut self) {
         match self.game_state {
-            GameState::StartingUp => {
-                
+            // the loading screen draws itself while it works
+            GameState::StartingUp => {},
+            GameState::Map => {
+                self.map.draw(&self.universe);
+                self.ui.draw();
             },
             GameState::Playing => {
-                for system in self.universe.iter_mut() {
-                    system.draw(&self.camera);
+                self.draw_world();
+                self.ui.draw();
+            },
+            GameState::Paused | GameState::ConfirmQuit => {
+                self.draw_world();
+                draw_rectangle(0., 0., screen_width(), screen_height(), Color::new(0., 0., 0., 0.45));
+                let size = text_size("PAUSED", 48);
+                draw_ui_text("PAUSED", (screen_width() - size.x) / 2., screen_height() * 0.15, 48, WHITE);
+                self.ui.draw();
+            },
+            GameState::Menu | GameState::Quit => {},
+        }
+    }
+    fn draw_world(&mut self) {
+        self.background.draw(&self.universe.active_system().camera);
+        self.universe.draw(&mut self.render);
+        self.inspector.draw_selection(self.universe.active_system());
+        self.map.draw_nav_marker(&self.universe, &self.universe.active_system().camera);
+        draw_text(
+            &format!("camera: {} [{:?}]", self.universe.active_system().camera.mode.name(), CAMERA_MODE_KEY),
+            10.,
+            screen_height() - TASKBAR_HEIGHT - 10.,
+            18.,
+            GRAY,
+        );
+        if self.debug_overlay {
+            self.draw_debug_overlay();
+        }
+    }
+    // Keys the UI didn't use. Escape backs out one level: map to
+    // game, game to the pause menu, pause menu back to the game.
+    pub fn handle_inputs(&mut self) {
+        if is_key_pressed(KeyCode::Escape) {
+            match self.game_state {
+                GameState::Playing => self.set_state(GameState::Paused),
+                GameState::Map | GameState::Paused => self.set_state(GameState::Playing),
+                GameState::ConfirmQuit => self.set_state(self.quit_from),
+                _ => {},
+            }
+        } else if is_key_pressed(PAUSE_KEY) {
+            match self.game_state {
+                GameState::Playing | GameState::Map => self.set_state(GameState::Paused),
+                GameState::Paused => self.set_state(GameState::Playing),
+                _ => {},
+            }
+        }
+        if is_key_pressed(MAP_KEY) {
+            match self.game_state {
+                GameState::Playing => self.set_state(GameState::Map),
+                GameState::Map => self.set_state(GameState::Playing),
+                _ => {},
+            }
+        }
+        if is_key_pressed(KeyCode::F3) {
+            self.debug_overlay = !self.debug_overlay;
+        }
+    }
+    // The one place the state changes. Shows and hides whatever
+    // goes with the states on either side.
+    fn set_state(&mut self, to: GameState) {
+        let from = self.game_state;
+        if from == to {
+            return;
+        }
+        match from {
+            GameState::Paused => self.ui.hide(PAUSE_WINDOW),
+            GameState::ConfirmQuit => self.ui.hide(QUIT_WINDOW),
+            _ => {},
+        }
+        match to {
+            GameState::Paused => self.ui.show(PAUSE_WINDOW),
+            GameState::ConfirmQuit => {
+                self.quit_from = from;
+                self.ui.show(QUIT_WINDOW);
+            },
+            GameState::Map => self.map.reset_view(),
+            _ => {},
+        }
+        // nothing moved while we were frozen, and it shouldn't
+        // all jump forward to catch up either
+        if from.is_frozen() && !to.is_frozen() {
+            self.universe.reset_clocks();
+        }
+        self.game_state = to;
+    }
+    fn draw_debug_overlay(&self) {
+        let system = self.universe.active_system();
+        let stats = system.render_stats;
+        let lines = [
+            format!("fps: {}", get_fps()),
+            format!("seed: {}", self.seed),
+            format!("system: {}", self.universe.active_entry().name),
+            format!("bodies: {} drawn ({} as dots), {} culled", stats.drawn, stats.lod, stats.culled),
+            format!(
+                "textures: {} cached ({} MB), {} generating",
+                self.render.len(),
+                self.render.bytes() / (1024 * 1024),
+                self.render.pending(),
+            ),
+            format!("zoom: {:.4}", system.camera.zoom),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line, 10., 20. + i as f32 * 18., 18., GREEN);
+        }
+    }
+    // the flags say whether the UI left the mouse / keyboard alone
+    fn handle_camera_input(&mut self, mouse: bool, keyboard: bool) {
+        let system = self.universe.active_system_mut();
+        // lock mode locks onto whatever's in the inspector, or
+        // failing that whatever's targeted on the map
+        let locked = match (self.inspector.selected, self.map.nav_target) {
+            (Some(id), _) => Some(id),
+            (None, Some(NavTarget::Body(id))) => Some(id),
+            _ => None,
+        };
+        let camera = &mut system.camera;
+        camera.locked_body = locked;
+        camera.handle_input(mouse, keyboard);
+        if camera.mode != self.settings.camera_mode {
+            self.settings.camera_mode = camera.mode;
+            self.settings.save();
+        }
+    }
+    // Runs this frame's input through the windows first. The
+    // response says what they used up, the rest is gameplay's.
+    fn update_ui(&mut self) -> UiResponse {
+        let mut response = self.ui.route(&UiInput::gather());
+        for action in response.actions.drain(..) {
+            self.handle_ui_action(action);
+        }
+        response
+    }
+    fn handle_ui_action(&mut self, action: UiAction) {
+        match action {
+            UiAction::Quit => self.set_state(GameState::Quit),
+            UiAction::QuitToMenu => self.set_state(GameState::Menu),
+            UiAction::Pause => self.set_state(GameState::Paused),
+            UiAction::Resume => self.set_state(GameState::Playing),
+            UiAction::CloseWindow(QUIT_WINDOW) => self.set_state(self.quit_from),
+            UiAction::CloseWindow(PAUSE_WINDOW) => self.set_state(GameState::Playing),
+            UiAction::CloseWindow(INSPECTOR_WINDOW) => { self.inspector.selected = None },
+            UiAction::CloseWindow(id) => { self.ui.hide(id) },
+            UiAction::WindowMoved(id) => {
+                if let Some(position) = self.ui.position(id) {
+                    self.settings.window_positions.insert(id.to_string(), (position.x, position.y));
+                    self.settings.save();
                 }
-                for e in self.ui_elements.iter_mut() {
-                    e.update(&mut self.quitting);
-                    e.draw();
+            },
+            UiAction::ShowWindow(QUIT_WINDOW) => self.set_state(GameState::ConfirmQuit),
+            UiAction::ShowWindow(SETTINGS_WINDOW) => { show_settings_window(&mut self.ui, &self.settings) },
+            UiAction::ShowWindow(SAVES_WINDOW) => { self.saves.show(&mut self.ui) },
+            UiAction::ShowWindow(id) => { self.ui.show(id) },
+            UiAction::ToggleMap => {
+                match self.game_state {
+                    GameState::Map => self.set_state(GameState::Playing),
+                    _ => self.set_state(GameState::Map),
                 }
-                self.handle_inputs();
             },
-            GameState::AboutToQuit => {
-                for e in self.ui_elements.iter_mut() {
-                    e.draw();
+            UiAction::ToggleDebugOverlay => { self.debug_overlay = !self.debug_overlay },
+            UiAction::SetCameraMode(mode) => {
+                self.universe.active_system_mut().camera.set_mode(mode);
+                self.settings.camera_mode = mode;
+                self.settings.save();
+            },
+            UiAction::SetAutosaveMinutes(minutes) => {
+                self.settings.autosave_minutes = minutes;
+                self.settings.save();
+            },
+            UiAction::SaveGame => {
+                match save_game(&quicksave_path(), &self.universe) {
+                    Ok(()) => println!("INFO: saved to {}", quicksave_path().display()),
+                    Err(e) => println!("WARNING: couldn't save: {}", e),
+                }
+            },
+            UiAction::SelectSave(i) => self.saves.select(i),
+            UiAction::LoadGame => {
+                match self.saves.load_selected() {
+                    // the actual rebuilding happens behind the
+                    // loading screen in `start_up`
+                    Ok(save) => {
+                        self.ui.hide(SAVES_WINDOW);
+                        self.pending_save = Some(save);
+                        self.set_state(GameState::StartingUp);
+                    },
+                    Err(e) => println!("WARNING: couldn't load: {}", e),
                 }
             },
-            GameState::Quit => { std::process::exit(0) },
+            // those are main menu buttons
+            UiAction::NewGame | UiAction::Continue => {},
         }
     }
-    pub fn handle_inputs(&mut self) {
-        if is_key_pressed(KeyCode::Escape) {
-            match &self.quitting{
-                Quitter::No => { self.quitting = Quitter::Maybe },
-                Quitter::Maybe => { self.quitting = Quitter::No },
-                Quitter::Yes => { self.game_state = GameState::Quit },
+    // Builds the first system (or restores a save) behind a
+    // loading screen, then we're off.
+    async fn start_up(&mut self) {
+        if let Some(save) = self.pending_save.take() {
+            let mut loading = Loading::new("Loading saved game...");
+            loading.show().await;
+            match restore(&save, &mut loading).await {
+                Ok(universe) => {
+                    self.universe = universe;
+                    self.seed = save.seed;
+                    self.settings.last_seed = Some(save.seed);
+                    self.settings.save();
+                    // the targeted and selected body ids belong to the old universe
+                    self.map.nav_target = None;
+                    self.inspector.selected = None;
+                    if let Some(player) = self.universe.active_system().player() {
+                        self.player = player.clone();
+                    }
+                    self.last_autosave = self.universe.sim_time;
+                    self.prepare_system(&mut loading).await;
+                    println!("INFO: loaded save, universe seed {}", save.seed);
+                    self.set_state(GameState::Playing);
+                    return;
+                },
+                Err(e) => println!("WARNING: couldn't load: {}", e),
             }
-        } 
+            // loaded mid-game, the old universe is still fine
+            if self.universe.entries[self.universe.active].system.is_some() {
+                self.set_state(GameState::Paused);
+                return;
+            }
+        }
+        let mut loading = Loading::new("Building the universe...");
+        loading.show().await;
+        self.universe.start(self.player.clone(), &mut loading).await;
+        self.universe.active_system_mut().camera.set_mode(self.settings.camera_mode);
+        self.prepare_system(&mut loading).await;
+        self.set_state(GameState::Playing);
     }
-    pub fn handle_quitter(&mut self) {
-        for e in self.ui_elements.iter_mut() {
-            e.update(&mut self.quitting);
-        }  
+
+    // everything drawing needs for the system we just landed in
+    async fn prepare_system(&mut self, loading: &mut Loading) {
+        self.render.prepare(self.universe.active_system(), loading).await;
+        self.background = Background::new(self.universe.active_system().seed);
     }
 }
+
+impl Game {
+    // Autosaves go by time actually played, so sitting on the pause
+    // menu for an hour doesn't fill every slot with the same thing
+    fn autosave_if_due(&mut self) {
+        let minutes = self.settings.autosave_minutes;
+        if minutes == 0 || self.universe.sim_time - self.last_autosave < minutes as f64 * 60. {
+            return;
+        }
+        self.last_autosave = self.universe.sim_time;
+        let path = autosave_path(save_dir(), next_autosave_slot(save_dir()));
+        match save_game(&path, &self.universe) {
+            Ok(()) => println!("INFO: autosaved to {}", path.display()),
+            Err(e) => println!("WARNING: autosave failed: {}", e),
+        }
+    }
+}
+
+fn pause_window() -> VintageWindow {
+    let buttons = [
+        ("Resume", UiAction::Resume),
+        ("Settings", UiAction::ShowWindow(SETTINGS_WINDOW)),
+        ("Save game", UiAction::SaveGame),
+        ("Load game", UiAction::ShowWindow(SAVES_WINDOW)),
+        ("Quit to menu", UiAction::QuitToMenu),
+        ("Quit game", UiAction::ShowWindow(QUIT_WINDOW)),
+    ];
+    let content: Vec<Box<dyn Widget>> = buttons.iter()
+        .map(|(text, action)| Box::new(Button::new(text, action.clone())) as Box<dyn Widget>)
+        .collect();
+    let mut window = VintageWindow::new(PAUSE_WINDOW, "Paused", content, None);
+    window.min_width = 220.;
+    window.relayout();
+    window
+}
+
+// What the seed box on the main menu starts out with. Pass
+// `--seed <n>` to get the exact same universe back (handy for
+// reproducing bugs), otherwise it's a fresh one off the clock.
+// `Game::new` prints whichever seed ends up used.
+pub fn universe_seed() -> u64 {
+    let args: Vec<String> = std::env::args().collect();
+    let from_args = args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|s| s.parse::<u64>().ok());
+    match from_args {
+        Some(seed) => seed,
+        None => {
+            let since_epoch = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)
+                .expect("Time went backwards");
+            since_epoch.as_secs() * 1_000_000_000 + since_epoch.subsec_nanos() as u64
+        },
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum GameState {
-    Playing,
+    // building the first system behind the loading screen
     StartingUp,
-    AboutToQuit,
+    Playing,
+    // the system / galaxy map is up, the world keeps going
+    Map,
+    Paused,
+    // the "you really tryna quit?" dialog is up
+    ConfirmQuit,
+    // back out to the main menu
+    Menu,
     Quit,
 }
-pub enum Quitter {
-    No, 
-    Yes,
-    Maybe,
+
+impl GameState {
+    // physics doesn't run in these
+    pub fn is_frozen(&self) -> bool {
+        matches!(self, GameState::StartingUp | GameState::Paused | GameState::ConfirmQuit)
+    }
 }
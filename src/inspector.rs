@@ -0,0 +1,181 @@
+use macroquad::prelude::*;
+
+use super::physics::*;
+use super::star_system::*;
+use super::star::*;
+use super::rockybody::*;
+use super::player::*;
+use super::ships::*;
+use super::menu::*;
+use super::camera::*;
+use super::ui::*;
+use super::widgets::*;
+
+pub const INSPECTOR_WINDOW: &str = "inspector";
+
+// how many screen pixels off a body you can click and still get it
+const PICK_SLOP_PIXELS: f32 = 8.;
+
+// Picks bodies out of the world and keeps a Win98 window up with
+// everything we know about whichever one was last clicked on. The
+// window itself lives in the UI stack like any other.
+pub struct Inspector {
+    // one of the active system's bodies
+    pub selected: Option<BodyId>,
+    // what the window was last popped up for
+    shown: Option<BodyId>,
+}
+
+impl Default for Inspector {
+    fn default() -> Inspector {
+        Inspector::new()
+    }
+}
+
+impl Inspector {
+    pub fn new() -> Inspector {
+        Inspector {
+            selected: None,
+            shown: None,
+        }
+    }
+
+    pub fn window() -> VintageWindow {
+        let mut window = VintageWindow::new(INSPECTOR_WINDOW, "Inspector", Vec::new(), None);
+        window.min_width = 380.;
+        window.set_position(vec2(40., 80.));
+        window
+    }
+
+    // Clicking a body selects it. Only call this with clicks the UI
+    // didn't already take. Returns true if something got picked.
+    pub fn update(&mut self, system: &StarSystem) -> bool {
+        // gone, merged into something bigger
+        if let Some(id) = self.selected {
+            if body_index(&system.bodies, id).is_none() {
+                self.selected = None;
+            }
+        }
+        if !is_mouse_button_pressed(MouseButton::Left) {
+            return false;
+        }
+
+        let (mouse_x, mouse_y) = mouse_position();
+        let (world_x, world_y) = system.camera.screen_to_world(mouse_x, mouse_y);
+        let slop = PICK_SLOP_PIXELS / system.camera.zoom as f32;
+        if let Some(i) = system.body_at(world_x, world_y, slop) {
+            self.selected = Some(system.bodies[i].id());
+            return true;
+        }
+        false
+    }
+
+    // Fills the window in from the current selection, popping it
+    // up when something new gets picked (even if it was minimized)
+    // and closing it when nothing is.
+    pub fn refresh(&mut self, ui: &mut UiStack, system: &StarSystem, system_name: &str) {
+        let (id, i) = match self.selected.and_then(|id| Some((id, body_index(&system.bodies, id)?))) {
+            Some(found) => found,
+            None => {
+                if self.shown.take().is_some() {
+                    ui.hide(INSPECTOR_WINDOW);
+                }
+                return;
+            },
+        };
+        let lines = describe_body(system, i, system_name);
+        if let Some(window) = ui.get_mut(INSPECTOR_WINDOW).and_then(|e| e.as_any_mut().downcast_mut::<VintageWindow>()) {
+            window.set_title(&lines[0]);
+            window.set_content(
+                lines.iter().skip(1)
+                    .map(|line| Box::new(Label::new(line)) as Box<dyn Widget>)
+                    .collect()
+            );
+        }
+        if self.shown != Some(id) || !ui.is_open(INSPECTOR_WINDOW) {
+            ui.show(INSPECTOR_WINDOW);
+            self.shown = Some(id);
+        }
+    }
+
+    // ring around the selected body in the world view
+    pub fn draw_selection(&self, system: &StarSystem) {
+        let body = match self.selected.and_then(|id| body_index(&system.bodies, id)).and_then(|i| system.bodies.get(i)) {
+            Some(body) => body,
+            None => return,
+        };
+        let camera: &ZCamera = &system.camera;
+        let (x, y) = camera.world_to_screen(body.xpos(), body.ypos());
+        draw_circle_lines(x, y, camera.scale(body.radius()).max(4.) + 6., 2., YELLOW);
+    }
+}
+
+// First line is the window title, the rest is the readout
+fn describe_body(system: &StarSystem, i: usize, system_name: &str) -> Vec<String> {
+    let body = &*system.bodies[i];
+    let any = body.as_any();
+    let star = any.downcast_ref::<Star>();
+    let rocky_body = any.downcast_ref::<RockyBody>();
+
+    let kind = if star.is_some() {
+        "Star"
+    } else if rocky_body.is_some() {
+        "Rocky body"
+    } else if any.downcast_ref::<Player>().is_some() {
+        "Your ship"
+    } else if any.downcast_ref::<Ship>().is_some() {
+        "Ship"
+    } else {
+        "???"
+    };
+
+    let speed = (body.xvel() * body.xvel() + body.yvel() * body.yvel()).sqrt();
+    let mut lines = vec![
+        format!("{} {} - {}", system_name, i, kind),
+        format!("Mass: {:.3e}", body.mass() as f64),
+        format!("Radius: {:.1}", body.radius()),
+        format!("Velocity: ({:.1}, {:.1})  |v| = {:.1}", body.xvel(), body.yvel(), speed),
+    ];
+
+    match heaviest_body(&system.bodies) {
+        Some(c) if c != i => {
+            let central = &*system.bodies[c];
+            let orbit = orbital_elements(body, central);
+            if orbit.bound {
+                lines.push(format!("Orbit: a = {:.0}, e = {:.3}", orbit.semi_major, orbit.eccentricity));
+                lines.push(format!("       period = {:.0}s", orbit.period));
+            } else {
+                lines.push(format!("Orbit: escaping (e = {:.3})", orbit.eccentricity));
+            }
+            if let (Some(rocky_body), Some(central_star)) = (rocky_body, central.as_any().downcast_ref::<Star>()) {
+                let t = equilibrium_temperature(rocky_body, central_star);
+                lines.push(format!("Temperature: ~{:.0} K", t));
+            }
+        },
+        _ => lines.push(String::from("Orbit: (this is what everything orbits)")),
+    }
+    if let Some(star) = star {
+        lines.push(format!("Temperature: {:.0} K", star.temperature()));
+    }
+
+    let life = &system.life;
+    match rocky_body {
+        Some(rocky_body) if life.home_seed == Some(rocky_body.seed()) => {
+            lines.push(String::from("Civilization: yes!"));
+            lines.push(format!("  energy {:.2}  size {:.2}", life.energy_output, life.size));
+            lines.push(format!("  str {:.2}  dex {:.2}  con {:.2}", life.strength, life.dexterity, life.constitution));
+        },
+        Some(_) => lines.push(String::from("Civilization: none")),
+        None => {},
+    }
+    lines
+}
+
+// Blackbody equilibrium temperature from the star's light alone,
+// with a made up albedo. It's a game, not a paper.
+fn equilibrium_temperature(body: &RockyBody, star: &Star) -> f32 {
+    let albedo: f32 = 0.3;
+    let (dx, dy) = (body.xpos() - star.xpos(), body.ypos() - star.ypos());
+    let d = (dx * dx + dy * dy).sqrt().max(star.radius());
+    star.temperature() * (star.radius() / (2. * d)).sqrt() * (1. - albedo).powf(0.25)
+}
@@ -0,0 +1,28 @@
+// Everything lives in here so both the game (main.rs) and the
+// headless sim (bin/para-genesis-sim.rs) can get at it.
+pub mod player;
+pub mod game;
+pub mod menu;
+pub mod star;
+pub mod rockybody;
+pub mod physics;
+pub mod camera;
+pub mod ships;
+pub mod shipgen;
+pub mod texturetools;
+pub mod mathtools;
+pub mod life;
+pub mod star_system;
+pub mod galaxy;
+pub mod map;
+pub mod settings;
+pub mod inspector;
+pub mod ui;
+pub mod widgets;
+pub mod taskbar;
+pub mod loading;
+pub mod main_menu;
+pub mod save;
+pub mod save_browser;
+pub mod render;
+pub mod background;
@@ -1,13 +1,14 @@
 use macroquad::prelude::*;
 use std::time::Instant;
-use ::rand::Rng;
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
 use std::any::Any;
 use noise::{NoiseFn, Perlin};
 use ::rand::distributions::{Distribution, Uniform};
 
 use super::ships::*;
 use super::physics::*;
-use super::camera::*;
+use super::render::*;
 
 pub struct Civilization {
     pub energy_output: f64,
@@ -16,8 +17,9 @@ pub struct Civilization {
     pub constitution: f64,
     pub damage: f64,
     pub size: f64,
-    pub seed: u32,
-    pub density_field: Texture2D,
+    pub seed: u64,
+    // seed of the rocky body they live on, if any
+    pub home_seed: Option<u64>,
 } 
 
 impl Civilization {
@@ -29,8 +31,7 @@ impl Civilization {
         active_ships: Vec<usize>,
         damage: f64,
         size: f64,
-        seed: u32,
-        density_field: Texture2D,
+        seed: u64,
     ) -> Civilization{
         Civilization {
             energy_output,
@@ -40,21 +41,19 @@ impl Civilization {
             damage,
             size,
             seed,
-            density_field,
+            home_seed: None,
         }
     }
 
-    pub fn new_rand( /*bodies: &mut Vec<Box<dyn PhysObj>> */ ) -> Civilization {
+    pub fn new_rand( /*bodies: &mut Vec<Box<dyn PhysObj>>, */ seed: u64) -> Civilization {
 
-        let mut rng = ::rand::thread_rng();
+        let mut rng = StdRng::seed_from_u64(seed);
         let energy_output = rng.gen_range(0..1000000000) as f64 / 1000000000.;
         let dexterity = rng.gen_range(0..1000000000) as f64 / 1000000000.;
         let strength = rng.gen_range(0..1000000000) as f64 / 1000000000.;
         let constitution = rng.gen_range(0..1000000000) as f64 / 1000000000.;
         let size = rng.gen_range(0..1000000000) as f64 / 1000000000.;
         let damage = 0.;
-        let seed = 0;
-        let density_field = Texture2D::from_image(&Image::gen_image_color(1000, 1000, WHITE));
 /*
         let active_ships = create_num_active_ships(
             ((strength + energy_output) / 2.) * size * 200.,
@@ -69,7 +68,7 @@ impl Civilization {
             damage,
             size,
             seed,
-            density_field,
+            home_seed: None,
         };
 
     }
@@ -86,6 +85,8 @@ impl PhysObj for Civilization {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+    fn id(&self) -> BodyId { 0 }
+    fn appearance(&self) -> Option<Appearance> { None }
     // We don't really wanna be able to change 
     // or meaningfully access any of
     // these kinds values for a Civlization
@@ -121,60 +122,48 @@ impl PhysObj for Civilization {
         Vec::new()
     }
 
+    fn reset_clock(&mut self) {
+    }
+
     fn update(&mut self) {
 
     }
 
-    fn draw(&mut self, camera: &ZCamera) {
-        // TODO draw everything related to the civlization
-        let (width, height) = (self.density_field.width(), self.density_field.height());
-        // WARNING - This is temp. In the future we will want to fix its center pos to the 
-        // actual map center of the solar system. Right now i havent specified anything about the
-        // camera
-        //
-        // maybe im being silly though and forgetting how the camera works tho so ill have to look
-        // into it.
-        draw_texture_ex(
-            &self.density_field,
-            (screen_width() as f32 / 2.) - (width as f32 / 2.),
-            (screen_height() as f32 / 2.) - (height as f32 / 2.),
-            WHITE,
-            DrawTextureParams {
-                ..Default::default()
-            }
-        );
-        println!("INFO: Drew texture.");
+    fn step(&mut self, _dt: f32) {
     }
 
 
 }
 
+// Ships land anywhere within `spread` * 2 of the origin either way.
+// Pass it in (LAYOUT_WIDTH, same as the rest of the system) rather
+// than going off the window, so this works headless too.
 pub fn create_n_active_ships(
     n: f64,
     bodies: &mut Vec<Box<dyn PhysObj>>,
-
+    spread: f32,
+    seed: u64,
 ) -> Vec<usize> {
     let mut ships_idx: Vec<usize> = Vec::new();
-    let mut rng = ::rand::thread_rng();
-    let width = screen_width();
-//    let height = screen_height();
+    let mut rng = StdRng::seed_from_u64(seed);
     let n = n as usize;
     for _ in 0..n {
         bodies.push(
             Box::new(
                 Ship {
-                    xpos: rng.gen_range(-width * 2. .. width * 2.),
-                    ypos: rng.gen_range(-width * 2. .. width * 2.),
+                    xpos: rng.gen_range(-spread * 2. .. spread * 2.),
+                    ypos: rng.gen_range(-spread * 2. .. spread * 2.),
                     desired_x: 0.,
                     desired_y: 0.,
                     xvel: 0.,
                     yvel: 0.,
                     force_vectors: Vec::new(),
+                    id: next_body_id(),
                     last_update: Instant::now(),
                     mass: 100000000,
                     radius: 22.,
                     max_dv: 40.,
-                    texture: create_ship_texture(22.),
+                    seed: rng.gen(),
                 }
             )
         );
@@ -184,8 +173,9 @@ pub fn create_n_active_ships(
 }
 
 pub fn load_civilization (
+    seed: u64,
 ) -> Civilization {
-    Civilization::new_rand()
+    Civilization::new_rand(seed)
 }
 
 pub fn update_ships_desired_pos(
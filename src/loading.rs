@@ -0,0 +1,106 @@
+use std::time::{Instant, Duration};
+use macroquad::prelude::*;
+
+use super::ui::*;
+
+// How long generation gets to run before we hand a frame back to
+// the window. Short enough that it never looks hung, long enough
+// that drawing the bar isn't most of the work.
+const FRAME_BUDGET: Duration = Duration::from_millis(30);
+
+const BAR_WIDTH: f32 = 420.;
+const BAR_HEIGHT: f32 = 22.;
+const BLOCK_WIDTH: f32 = 10.;
+
+// Progress for anything slow enough to need a loading screen
+// (generating a system, mostly). Whatever's doing the work says
+// how much there is with `expect` and calls `tick` after each
+// piece, and every so often that draws the screen and waits for
+// the next frame.
+pub struct Loading {
+    title: String,
+    stage: String,
+    done: usize,
+    total: usize,
+    last_yield: Instant,
+    // just counts, never draws (no window to draw in)
+    silent: bool,
+}
+
+impl Loading {
+    pub fn new(title: &str) -> Loading {
+        Loading {
+            title: String::from(title),
+            stage: String::new(),
+            done: 0,
+            total: 0,
+            last_yield: Instant::now(),
+            silent: false,
+        }
+    }
+
+    // For the headless sim, same progress counting with no screen
+    pub fn silent(title: &str) -> Loading {
+        let mut loading = Loading::new(title);
+        loading.silent = true;
+        loading
+    }
+
+    // `n` more pieces of work are on the way
+    pub fn expect(&mut self, n: usize) {
+        self.total += n;
+    }
+
+    pub fn stage(&mut self, stage: &str) {
+        self.stage = String::from(stage);
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            return 0.;
+        }
+        (self.done as f32 / self.total as f32).min(1.)
+    }
+
+    pub async fn tick(&mut self) {
+        self.done += 1;
+        if !self.silent && self.last_yield.elapsed() >= FRAME_BUDGET {
+            self.draw();
+            next_frame().await;
+            self.last_yield = Instant::now();
+        }
+    }
+
+    // Shows the screen right away, so there's something up before
+    // the first slow piece of work even starts
+    pub async fn show(&mut self) {
+        if self.silent {
+            return;
+        }
+        self.draw();
+        next_frame().await;
+        self.last_yield = Instant::now();
+    }
+
+    pub fn draw(&self) {
+        clear_background(BLACK);
+        let (cx, cy) = (screen_width() / 2., screen_height() / 2.);
+        let title = text_size(&self.title, 32);
+        draw_ui_text(&self.title, cx - title.x / 2., cy - 80., 32, WHITE);
+
+        // the classic chunky blue progress bar
+        let bar = Rect::new((cx - BAR_WIDTH / 2.).floor(), cy.floor(), BAR_WIDTH, BAR_HEIGHT);
+        draw_rectangle(bar.x - 8., bar.y - 8., bar.w + 16., bar.h + 16., WIN98_FACE);
+        draw_bevel(Rect::new(bar.x - 8., bar.y - 8., bar.w + 16., bar.h + 16.), true);
+        draw_rectangle(bar.x, bar.y, bar.w, bar.h, WIN98_FIELD);
+        draw_bevel(bar, false);
+        let blocks = ((bar.w - 4.) * self.fraction() / BLOCK_WIDTH) as i32;
+        for i in 0..blocks {
+            draw_rectangle(bar.x + 3. + i as f32 * BLOCK_WIDTH, bar.y + 3., BLOCK_WIDTH - 2., bar.h - 6., WIN98_TITLE);
+        }
+
+        let status = format!("{} ({}/{})", self.stage, self.done.min(self.total), self.total);
+        let size = text_size(&status, FONT_SIZE);
+        draw_ui_text(&status, cx - size.x / 2., bar.y + bar.h + 20., FONT_SIZE, GRAY);
+    }
+}
@@ -0,0 +1,198 @@
+use macroquad::prelude::*;
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
+
+use super::menu::*;
+use super::settings::*;
+use super::mathtools::*;
+use super::ui::*;
+use super::widgets::*;
+use super::save::*;
+use super::save_browser::*;
+
+const MAIN_MENU_WINDOW: &str = "main_menu";
+const SEED_ALERT_WINDOW: &str = "bad_seed";
+const BACKDROP_STARS: usize = 400;
+
+pub enum MenuChoice {
+    // start (or go back to) the universe with this seed
+    Play(u64),
+    // pick up where the last save left off
+    Load(SaveFile),
+    Quit,
+}
+
+// Title screen. Runs its own little loop until the player picks
+// something, then hands back what they picked.
+pub struct MainMenu {
+    ui: UiStack,
+    settings: Settings,
+    saves: SaveBrowser,
+    // (x, y, twinkle phase) as fractions of the screen
+    backdrop: Vec<(f32, f32, f32)>,
+}
+
+impl MainMenu {
+    pub fn new(default_seed: u64) -> MainMenu {
+        let settings = Settings::load();
+        let mut ui = UiStack::new(vec![
+            (String::from("Load game"), UiAction::ShowWindow(SAVES_WINDOW)),
+            (String::from("Settings"), UiAction::ShowWindow(SETTINGS_WINDOW)),
+            (String::from("Quit"), UiAction::Quit),
+        ]);
+        ui.push(Box::new(VintageWindow::new(
+            MAIN_MENU_WINDOW,
+            "Welcome to PARA-GENESIS",
+            main_menu_content(&default_seed.to_string(), settings.last_seed),
+            None,
+        )));
+        ui.push(Box::new(settings_window(&settings)));
+        ui.push(Box::new(SaveBrowser::window()));
+        ui.push(Box::new(VintageWindow::message(
+            SEED_ALERT_WINDOW,
+            "Bad seed",
+            &["Type a seed in first,", "any number or word will do."],
+            "OK",
+            UiAction::CloseWindow(SEED_ALERT_WINDOW),
+            WindowType::Alert,
+            None,
+        )));
+        ui.restore_positions(&settings.window_positions);
+        ui.show(MAIN_MENU_WINDOW);
+
+        let mut rng = StdRng::seed_from_u64(default_seed);
+        let backdrop = (0..BACKDROP_STARS)
+            .map(|_| (rng.gen_range(0.0..1.0), rng.gen_range(0.0..1.0), rng.gen_range(0.0..std::f32::consts::TAU)))
+            .collect();
+
+        MainMenu {
+            ui,
+            settings,
+            saves: SaveBrowser::new(),
+            backdrop,
+        }
+    }
+
+    pub async fn run(&mut self) -> MenuChoice {
+        // a game might have changed these since we last looked,
+        // and there may be something to Continue now
+        self.settings = Settings::load();
+        let last_seed = self.settings.last_seed;
+        if let Some(window) = self.main_window() {
+            let seed_text = window.text_value().unwrap_or_default();
+            window.set_content(main_menu_content(&seed_text, last_seed));
+        }
+        self.ui.show(MAIN_MENU_WINDOW);
+        loop {
+            clear_background(BLACK);
+            self.draw_backdrop();
+
+            let mut response = self.ui.route(&UiInput::gather());
+            for action in response.actions.drain(..) {
+                if let Some(choice) = self.handle_action(action) {
+                    return choice;
+                }
+            }
+            // nothing to go back to from here
+            if !self.ui.is_open(MAIN_MENU_WINDOW) {
+                self.ui.show(MAIN_MENU_WINDOW);
+            }
+
+            self.ui.draw();
+            next_frame().await
+        }
+    }
+
+    fn handle_action(&mut self, action: UiAction) -> Option<MenuChoice> {
+        match action {
+            UiAction::NewGame => {
+                let text = self.main_window()
+                    .and_then(|window| window.text_value())
+                    .unwrap_or_default();
+                if text.trim().is_empty() {
+                    self.ui.show(SEED_ALERT_WINDOW);
+                    return None;
+                }
+                return Some(MenuChoice::Play(seed_from_text(&text)));
+            },
+            UiAction::Continue => {
+                // the newest save if there is one, otherwise at
+                // least the same universe from the start
+                if let Some(newest) = list_saves().first() {
+                    match read_save(&newest.path) {
+                        Ok(save) => return Some(MenuChoice::Load(save)),
+                        Err(e) => println!("WARNING: couldn't continue from {}: {}", newest.path.display(), e),
+                    }
+                }
+                if let Some(seed) = self.settings.last_seed {
+                    return Some(MenuChoice::Play(seed));
+                }
+            },
+            UiAction::SelectSave(i) => self.saves.select(i),
+            UiAction::LoadGame => {
+                match self.saves.load_selected() {
+                    Ok(save) => {
+                        self.ui.hide(SAVES_WINDOW);
+                        return Some(MenuChoice::Load(save));
+                    },
+                    Err(e) => println!("WARNING: couldn't load: {}", e),
+                }
+            },
+            UiAction::Quit => return Some(MenuChoice::Quit),
+            UiAction::ShowWindow(SETTINGS_WINDOW) => show_settings_window(&mut self.ui, &self.settings),
+            UiAction::ShowWindow(SAVES_WINDOW) => self.saves.show(&mut self.ui),
+            UiAction::ShowWindow(id) => self.ui.show(id),
+            UiAction::CloseWindow(id) => self.ui.hide(id),
+            UiAction::SetCameraMode(mode) => {
+                self.settings.camera_mode = mode;
+                self.settings.save();
+            },
+            UiAction::SetAutosaveMinutes(minutes) => {
+                self.settings.autosave_minutes = minutes;
+                self.settings.save();
+            },
+            UiAction::WindowMoved(id) => {
+                if let Some(position) = self.ui.position(id) {
+                    self.settings.window_positions.insert(id.to_string(), (position.x, position.y));
+                    self.settings.save();
+                }
+            },
+            // in-game only
+            _ => {},
+        }
+        None
+    }
+
+    fn main_window(&mut self) -> Option<&mut VintageWindow> {
+        self.ui.get_mut(MAIN_MENU_WINDOW).and_then(|e| e.as_any_mut().downcast_mut::<VintageWindow>())
+    }
+
+    fn draw_backdrop(&self) {
+        let t = get_time() as f32;
+        for (x, y, phase) in self.backdrop.iter() {
+            let brightness = 0.55 + 0.45 * (t * 1.3 + phase).sin();
+            draw_rectangle(x * screen_width(), y * screen_height(), 2., 2., Color::new(1., 1., 1., brightness));
+        }
+        let title = "PARA-GENESIS";
+        let size = text_size(title, 72);
+        draw_ui_text(title, (screen_width() - size.x) / 2., screen_height() * 0.12, 72, WHITE);
+    }
+}
+
+fn main_menu_content(seed_text: &str, last_seed: Option<u64>) -> Vec<Box<dyn Widget>> {
+    let mut buttons: Vec<Box<dyn Widget>> = vec![
+        Box::new(Button::new("New Game", UiAction::NewGame)),
+    ];
+    if last_seed.is_some() {
+        buttons.push(Box::new(Button::new("Continue", UiAction::Continue)));
+    }
+    buttons.push(Box::new(Button::new("Load Game", UiAction::ShowWindow(SAVES_WINDOW))));
+    buttons.push(Box::new(Button::new("Settings", UiAction::ShowWindow(SETTINGS_WINDOW))));
+    buttons.push(Box::new(Button::new("Quit", UiAction::Quit)));
+
+    vec![
+        Box::new(Label::new("Universe seed:")),
+        Box::new(TextInput::new(seed_text, Some(|_| UiAction::NewGame))),
+        Box::new(Centered { child: Box::new(Row { children: buttons }) }),
+    ]
+}
@@ -0,0 +1,418 @@
+use macroquad::prelude::*;
+
+use super::physics::*;
+use super::galaxy::*;
+use super::star_system::*;
+use super::star::*;
+use super::rockybody::*;
+use super::player::*;
+use super::ships::*;
+use super::camera::*;
+
+pub const MAP_KEY: KeyCode = KeyCode::M;
+
+// `zoom` is relative to "the whole thing fits on screen" for
+// whichever view we're in. Zooming the system view out past
+// SYSTEM_MIN_ZOOM flips over to the galaxy, and zooming the
+// galaxy in past GALAXY_MAX_ZOOM flips back.
+const SYSTEM_MIN_ZOOM: f32 = 0.5;
+const SYSTEM_MAX_ZOOM: f32 = 300.;
+const GALAXY_MIN_ZOOM: f32 = 0.5;
+const GALAXY_MAX_ZOOM: f32 = 12.;
+const ZOOM_STEP: f32 = 1.15;
+
+// how close (in screen pixels) a click has to be to pick something
+const PICK_RADIUS: f32 = 12.;
+
+// player path prediction
+const PREDICT_STEPS: usize = 600;
+const PREDICT_DT: f64 = 1.;
+
+const ORBIT_SEGMENTS: usize = 48;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MapView {
+    System,
+    Galaxy,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum NavTarget {
+    // one of the active system's bodies
+    Body(BodyId),
+    // index into `Galaxy::entries`
+    System(usize),
+}
+
+// Whether it's showing is up to the game state (`GameState::Map`)
+pub struct SystemMap {
+    pub view: MapView,
+    pub nav_target: Option<NavTarget>,
+    zoom: f32,
+    // pan, in world units of whichever view we're in
+    offset: Vec2,
+    last_drag: Option<Vec2>,
+}
+
+impl Default for SystemMap {
+    fn default() -> SystemMap {
+        SystemMap::new()
+    }
+}
+
+impl SystemMap {
+    pub fn new() -> SystemMap {
+        SystemMap {
+            view: MapView::System,
+            nav_target: None,
+            zoom: 1.,
+            offset: Vec2::ZERO,
+            last_drag: None,
+        }
+    }
+
+    // every time the map opens it starts on the current system
+    pub fn reset_view(&mut self) {
+        self.view = MapView::System;
+        self.zoom = 1.;
+        self.offset = Vec2::ZERO;
+    }
+
+    // screen pixels per world unit for the current view
+    fn scale(&self, galaxy: &Galaxy) -> f32 {
+        let fit = screen_width().min(screen_height()) * 0.45;
+        match self.view {
+            MapView::System => fit / galaxy.active_system().radius.max(1.) * self.zoom,
+            MapView::Galaxy => fit / galaxy_radius(galaxy) * self.zoom,
+        }
+    }
+
+    fn focus(&self, galaxy: &Galaxy) -> Vec2 {
+        match self.view {
+            MapView::System => Vec2::from(galaxy.active_system().origin) + self.offset,
+            MapView::Galaxy => {
+                let entry = galaxy.active_entry();
+                vec2(entry.xpos, entry.ypos) + self.offset
+            },
+        }
+    }
+
+    fn to_screen(&self, galaxy: &Galaxy, x: f32, y: f32) -> Vec2 {
+        let center = vec2(screen_width() / 2., screen_height() / 2.);
+        center + (vec2(x, y) - self.focus(galaxy)) * self.scale(galaxy)
+    }
+
+    pub fn update(&mut self, galaxy: &mut Galaxy) {
+        let (_, wheel) = mouse_wheel();
+        if wheel != 0. {
+            self.zoom *= ZOOM_STEP.powf(wheel.signum());
+        }
+        match self.view {
+            MapView::System => {
+                if self.zoom < SYSTEM_MIN_ZOOM {
+                    self.view = MapView::Galaxy;
+                    self.zoom = GALAXY_MAX_ZOOM;
+                    self.offset = Vec2::ZERO;
+                }
+                self.zoom = self.zoom.min(SYSTEM_MAX_ZOOM);
+            },
+            MapView::Galaxy => {
+                if self.zoom > GALAXY_MAX_ZOOM {
+                    self.view = MapView::System;
+                    self.zoom = SYSTEM_MIN_ZOOM;
+                    self.offset = Vec2::ZERO;
+                }
+                self.zoom = self.zoom.max(GALAXY_MIN_ZOOM);
+            },
+        }
+
+        // right drag pans
+        let mouse = Vec2::from(mouse_position());
+        if is_mouse_button_down(MouseButton::Right) {
+            if let Some(last) = self.last_drag {
+                self.offset -= (mouse - last) / self.scale(galaxy);
+            }
+            self.last_drag = Some(mouse);
+        } else {
+            self.last_drag = None;
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            match self.view {
+                MapView::System => {
+                    if let Some(i) = self.body_under(galaxy, mouse) {
+                        self.nav_target = Some(NavTarget::Body(galaxy.active_system().bodies[i].id()));
+                    }
+                },
+                MapView::Galaxy => {
+                    if let Some(i) = self.system_under(galaxy, mouse) {
+                        if i != galaxy.active {
+                            self.nav_target = Some(NavTarget::System(i));
+                            galaxy.destination = Some(i);
+                        }
+                    }
+                },
+            }
+        }
+
+        // targets go stale when the body they point at is gone,
+        // or once we've arrived at the system
+        match self.nav_target {
+            Some(NavTarget::Body(id)) if body_index(&galaxy.active_system().bodies, id).is_none() => self.nav_target = None,
+            Some(NavTarget::System(i)) if i == galaxy.active => self.nav_target = None,
+            _ => {},
+        }
+    }
+
+    fn body_under(&self, galaxy: &Galaxy, mouse: Vec2) -> Option<usize> {
+        let system = galaxy.active_system();
+        let mut best: Option<(usize, f32)> = None;
+        for (i, body) in system.bodies.iter().enumerate() {
+            let p = self.to_screen(galaxy, body.xpos(), body.ypos());
+            let d = p.distance(mouse);
+            let reach = PICK_RADIUS.max(icon_radius(&**body, self.scale(galaxy)));
+            if d <= reach && best.is_none_or(|(_, best_d)| d < best_d) {
+                best = Some((i, d));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    fn system_under(&self, galaxy: &Galaxy, mouse: Vec2) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for (i, entry) in galaxy.entries.iter().enumerate() {
+            let d = self.to_screen(galaxy, entry.xpos, entry.ypos).distance(mouse);
+            if d <= PICK_RADIUS && best.is_none_or(|(_, best_d)| d < best_d) {
+                best = Some((i, d));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    pub fn draw(&self, galaxy: &Galaxy) {
+        match self.view {
+            MapView::System => self.draw_system(galaxy),
+            MapView::Galaxy => self.draw_galaxy(galaxy),
+        }
+    }
+
+    fn draw_system(&self, galaxy: &Galaxy) {
+        let system = galaxy.active_system();
+        let scale = self.scale(galaxy);
+        let central = heaviest_body(&system.bodies);
+
+        // orbits first so everything else sits on top of them
+        if let Some(c) = central {
+            let star = &*system.bodies[c];
+            for (i, body) in system.bodies.iter().enumerate() {
+                if i == c || body.as_any().downcast_ref::<Player>().is_some() {
+                    continue;
+                }
+                let elements = orbital_elements(&**body, star);
+                if elements.bound {
+                    self.draw_orbit(galaxy, star, &elements, Color::new(0.5, 0.5, 0.6, 0.25));
+                }
+            }
+        }
+
+        if let Some(player) = system.player() {
+            self.draw_predicted_path(galaxy, system, player);
+        }
+
+        for (i, body) in system.bodies.iter().enumerate() {
+            let p = self.to_screen(galaxy, body.xpos(), body.ypos());
+            if !on_screen(p, 50.) {
+                continue;
+            }
+            let r = icon_radius(&**body, scale);
+            let any = body.as_any();
+            if any.downcast_ref::<Star>().is_some() {
+                draw_circle(p.x, p.y, r, Color::new(1., 0.85, 0.4, 1.));
+                if Some(i) == central {
+                    draw_label(&galaxy.active_entry().name, p, r, YELLOW);
+                }
+            } else if let Some(player) = any.downcast_ref::<Player>() {
+                draw_heading_marker(p, r, player.xvel(), player.yvel(), WHITE);
+                draw_label("YOU", p, r, WHITE);
+            } else if any.downcast_ref::<Ship>().is_some() {
+                draw_heading_marker(p, r, body.xvel(), body.yvel(), RED);
+            } else if any.downcast_ref::<RockyBody>().is_some() {
+                draw_circle(p.x, p.y, r, Color::new(0.3, 0.7, 0.8, 1.));
+                if self.zoom > 4. && body.radius() > 60. {
+                    draw_label(&format!("{} {}", galaxy.active_entry().name, i), p, r, LIGHTGRAY);
+                }
+            }
+
+            if self.nav_target == Some(NavTarget::Body(body.id())) {
+                draw_circle_lines(p.x, p.y, r + 6., 2., GREEN);
+                draw_label("TARGET", vec2(p.x, p.y + 16.), r, GREEN);
+            }
+        }
+
+        draw_map_header(&format!(
+            "SYSTEM MAP - {}   [{:?}] close   [wheel] zoom   [right drag] pan   [click] set target",
+            galaxy.active_entry().name, MAP_KEY
+        ));
+    }
+
+    fn draw_orbit(&self, galaxy: &Galaxy, central: &dyn PhysObj, elements: &OrbitalElements, color: Color) {
+        // the central body sits at one focus, the ellipse center is
+        // a * e back along the periapsis direction
+        let (cos_w, sin_w) = (elements.arg_periapsis.cos(), elements.arg_periapsis.sin());
+        let c = elements.semi_major * elements.eccentricity;
+        let center = vec2(central.xpos() - c * cos_w, central.ypos() - c * sin_w);
+
+        let mut last: Option<Vec2> = None;
+        for k in 0..=ORBIT_SEGMENTS {
+            let t = k as f32 / ORBIT_SEGMENTS as f32 * std::f32::consts::TAU;
+            let (ex, ey) = (elements.semi_major * t.cos(), elements.semi_minor * t.sin());
+            let world = center + vec2(ex * cos_w - ey * sin_w, ex * sin_w + ey * cos_w);
+            let p = self.to_screen(galaxy, world.x, world.y);
+            if let Some(q) = last {
+                draw_line(q.x, q.y, p.x, p.y, 1., color);
+            }
+            last = Some(p);
+        }
+    }
+
+    fn draw_predicted_path(&self, galaxy: &Galaxy, system: &StarSystem, player: &Player) {
+        let path = predict_path(system, player, PREDICT_STEPS, PREDICT_DT);
+        let mut last = self.to_screen(galaxy, player.xpos(), player.ypos());
+        for (k, (x, y)) in path.into_iter().enumerate() {
+            let p = self.to_screen(galaxy, x, y);
+            let fade = 1. - k as f32 / PREDICT_STEPS as f32;
+            draw_line(last.x, last.y, p.x, p.y, 1.5, Color::new(0.3, 1., 1., 0.8 * fade));
+            last = p;
+        }
+    }
+
+    fn draw_galaxy(&self, galaxy: &Galaxy) {
+        for (i, entry) in galaxy.entries.iter().enumerate() {
+            let p = self.to_screen(galaxy, entry.xpos, entry.ypos);
+            if !on_screen(p, 50.) {
+                continue;
+            }
+            let visited = entry.system.is_some();
+            let color = if visited { Color::new(1., 0.9, 0.6, 1.) } else { Color::new(0.6, 0.6, 0.7, 0.8) };
+            draw_circle(p.x, p.y, if visited { 3. } else { 2. }, color);
+
+            if i == galaxy.active {
+                draw_circle_lines(p.x, p.y, 8., 2., WHITE);
+                draw_label(&format!("{} (you are here)", entry.name), p, 8., WHITE);
+            } else if galaxy.destination == Some(i) {
+                draw_circle_lines(p.x, p.y, 8., 2., GREEN);
+                draw_label(&entry.name, p, 8., GREEN);
+            } else if self.zoom > 4. || visited {
+                draw_label(&entry.name, p, 3., GRAY);
+            }
+        }
+
+        draw_map_header(&format!(
+            "GALAXY MAP - {} systems   [{:?}] close   [wheel] zoom   [right drag] pan   [click] set destination",
+            galaxy.entries.len(), MAP_KEY
+        ));
+    }
+
+    // Little marker on the regular view pointing at the nav target.
+    // Drawn on top of the body if it's on screen, otherwise pinned
+    // to the edge of the screen in the direction of the target.
+    pub fn draw_nav_marker(&self, galaxy: &Galaxy, camera: &ZCamera) {
+        let system = galaxy.active_system();
+        let target = match self.nav_target {
+            Some(NavTarget::Body(id)) => match body_index(&system.bodies, id).and_then(|i| system.bodies.get(i)) {
+                Some(body) => vec2(body.xpos(), body.ypos()),
+                None => return,
+            },
+            _ => return,
+        };
+        let p = Vec2::from(camera.world_to_screen(target.x, target.y));
+        let distance = match system.player() {
+            Some(player) => vec2(player.xpos(), player.ypos()).distance(target),
+            None => 0.,
+        };
+        if on_screen(p, 0.) {
+            draw_circle_lines(p.x, p.y, 14., 2., GREEN);
+            draw_label(&format!("{:.0}", distance), p, 14., GREEN);
+        } else {
+            let center = vec2(screen_width() / 2., screen_height() / 2.);
+            let dir = (p - center).normalize_or_zero();
+            let edge = center + dir * (screen_width().min(screen_height()) / 2. - 30.);
+            draw_heading_marker(edge, 10., dir.x, dir.y, GREEN);
+            draw_label(&format!("{:.0}", distance), edge, 10., GREEN);
+        }
+    }
+}
+
+fn galaxy_radius(galaxy: &Galaxy) -> f32 {
+    galaxy.entries.iter()
+        .map(|entry| (entry.xpos * entry.xpos + entry.ypos * entry.ypos).sqrt())
+        .fold(1., f32::max)
+}
+
+// Icons are sized by what kind of body it is, not by how big
+// it really is, otherwise everything but the star would be
+// invisible on the map. They do grow once you zoom way in.
+fn icon_radius(body: &dyn PhysObj, scale: f32) -> f32 {
+    let any = body.as_any();
+    let class_size = if any.downcast_ref::<Star>().is_some() {
+        9.
+    } else if any.downcast_ref::<Player>().is_some() || any.downcast_ref::<Ship>().is_some() {
+        6.
+    } else if body.radius() > 60. {
+        4.5
+    } else if body.radius() > 30. {
+        3.
+    } else {
+        2.
+    };
+    f32::max(class_size, body.radius() * scale)
+}
+
+// Steps the player forward using only the stars' gravity.
+// Good enough to see where you're headed without simulating
+// all 450 rocks.
+pub fn predict_path(system: &StarSystem, player: &Player, steps: usize, dt: f64) -> Vec<(f32, f32)> {
+    let stars: Vec<(f64, f64, f64)> = system.bodies.iter()
+        .filter(|body| body.as_any().downcast_ref::<Star>().is_some())
+        .map(|body| (body.xpos() as f64, body.ypos() as f64, G as f64 * body.mass() as f64))
+        .collect();
+
+    let (mut x, mut y) = (player.xpos() as f64, player.ypos() as f64);
+    let (mut vx, mut vy) = (player.xvel() as f64, player.yvel() as f64);
+    let mut path = Vec::with_capacity(steps);
+    for _ in 0..steps {
+        let (mut ax, mut ay) = (0., 0.);
+        for &(sx, sy, mu) in stars.iter() {
+            let (dx, dy) = (sx - x, sy - y);
+            let r2 = (dx * dx + dy * dy).max(1.);
+            let r = r2.sqrt();
+            ax += mu / r2 * dx / r;
+            ay += mu / r2 * dy / r;
+        }
+        vx += ax * dt;
+        vy += ay * dt;
+        x += vx * dt;
+        y += vy * dt;
+        path.push((x as f32, y as f32));
+    }
+    path
+}
+
+fn on_screen(p: Vec2, margin: f32) -> bool {
+    p.x >= -margin && p.y >= -margin && p.x <= screen_width() + margin && p.y <= screen_height() + margin
+}
+
+fn draw_heading_marker(p: Vec2, r: f32, vx: f32, vy: f32, color: Color) {
+    let dir = vec2(vx, vy).try_normalize().unwrap_or(vec2(0., -1.));
+    let side = vec2(-dir.y, dir.x);
+    draw_triangle(p + dir * r * 1.4, p - dir * r + side * r, p - dir * r - side * r, color);
+}
+
+fn draw_label(text: &str, p: Vec2, r: f32, color: Color) {
+    draw_text(text, p.x + r + 4., p.y - r - 2., 18., color);
+}
+
+fn draw_map_header(text: &str) {
+    draw_rectangle(0., 0., screen_width(), 28., Color::new(0., 0., 0., 0.7));
+    draw_text(text, 10., 19., 20., WHITE);
+}
@@ -49,3 +49,35 @@ pub fn r_from_mass(mass: f32, from_range: (f32, f32), to_range: (f32, f32)) -> f
 
 
 
+// splitmix64. Turns the one universe seed into as many
+// unrelated looking sub-seeds as we want (one per body,
+// one per texture frame, etc) so the same seed always
+// builds the exact same universe.
+pub fn sub_seed(seed: u64, salt: u64) -> u64 {
+    let mut z = seed ^ salt.wrapping_mul(0x9e3779b97f4a7c15);
+    z = z.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// Perlin only takes a u32 seed
+pub fn perlin_seed(seed: u64) -> u32 {
+    (seed ^ (seed >> 32)) as u32
+}
+
+// Seeds typed in by the player. Numbers are used as-is so
+// `--seed` values can be pasted back in, anything else gets
+// hashed (FNV-1a) so "bob's universe" is a valid seed too.
+pub fn seed_from_text(text: &str) -> u64 {
+    let text = text.trim();
+    if let Ok(seed) = text.parse::<u64>() {
+        return seed;
+    }
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in text.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
@@ -1,13 +1,14 @@
-use std::time::Instant;
+use std::any::Any;
 use macroquad::audio::*;
 use macroquad::prelude::*;
 use macroquad::texture::*;
-use super::physics::*;
-use super::camera::*;
-use std::any::Any;
-use super::game::*;
 
 use super::texturetools::*;
+use super::ui::*;
+use super::widgets::*;
+use super::taskbar::*;
+use super::settings::*;
+use super::camera::*;
 
 #[derive(Clone)]
 pub enum WindowType {
@@ -15,363 +16,314 @@ pub enum WindowType {
     Alert,
 }
 
+const TITLE_HEIGHT: f32 = 24.;
+const CLOSE_SIZE: f32 = 16.;
+
+// A Win98 window holding a column of widgets. It sizes itself
+// around them, so nothing in here is in percentages anymore.
+// Drag it by the title bar, or minimize it down to the taskbar.
 pub struct VintageWindow {
-    window_name: String,
-    message_text: String,
-    line_two: String,
-    button_text: String,
-    width: u16,
-    height: u16,
-    texture: Texture2D,
-    window_type: WindowType,
+    id: &'static str,
+    title: String,
+    content: Vec<Box<dyn Widget>>,
+    width: f32,
+    height: f32,
+    // stops windows with changing content from jittering about
+    pub min_width: f32,
+    // top left corner. None keeps it centered on screen
+    position: Option<Vec2>,
+    // where on the window it was grabbed, while being dragged
+    drag_from: Option<Vec2>,
     visible: bool,
-    sounds: Vec<Sound>,
+    minimized: bool,
+    // played whenever the window pops up
+    sound: Option<Sound>,
 }
 
 impl VintageWindow {
-    pub fn new(width: u16, height: u16, window_name: String, message_text: String, line_two: String, button_text: String, window_type: WindowType, input_sound: Sound) -> VintageWindow {
-        let sounds = vec![input_sound];
-        VintageWindow {
-            window_name,
-            message_text,
-            line_two,
-            button_text,
-            width,
-            height,
-            texture: create_vintage_window_texture(width, height, &window_type),
-            window_type,
+    pub fn new(id: &'static str, title: &str, content: Vec<Box<dyn Widget>>, sound: Option<Sound>) -> VintageWindow {
+        let mut window = VintageWindow {
+            id,
+            title: String::from(title),
+            content,
+            width: 0.,
+            height: 0.,
+            min_width: 0.,
+            position: None,
+            drag_from: None,
             visible: false,
-            sounds,
-        }        
+            minimized: false,
+            sound,
+        };
+        window.relayout();
+        window
     }
 
-    pub fn update(
-        &mut self,
-        quitting: &mut Quitter,
-    ) {
-        let draw_x = screen_width() / 2. - (self.width as f32 / 2.); 
-        let draw_y = screen_height() / 2. - (self.height as f32 / 2.);
-
-
-        let (mouse_x, mouse_y) = mouse_position(); 
-
-        let button_width = (self.width as f32 * 0.28) as u32;
-        let button_height = (self.height as f32 * 0.15) as u32;
-        let top_left_x = (self.width as f32 * 0.5) as u32 - (button_width / 2);
-        let top_left_y = (self.height as f32 * 0.7) as u32;
-
-        // top left corner of the square
-        let (x0, y0) = (self.width as f32 - self.width as f32 * 0.077, self.height as f32 * 0.025);
-
-        let side_len = (self.width as f32 - self.width as f32 * 0.01) as u32 - (self.width as f32 - self.width as f32 * 0.07) as u32;
-    
+    // The old "ERROR: QUITTER DETECTED" style box: icon on the left,
+    // lines of text next to it, one button underneath.
+    pub fn message(
+        id: &'static str,
+        title: &str,
+        lines: &[&str],
+        button_text: &str,
+        action: UiAction,
+        window_type: WindowType,
+        sound: Option<Sound>,
+    ) -> VintageWindow {
+        let text: Vec<Box<dyn Widget>> = lines.iter()
+            .map(|line| Box::new(Label::new(line)) as Box<dyn Widget>)
+            .collect();
+        let mut row: Vec<Box<dyn Widget>> = Vec::new();
+        if let WindowType::Error = window_type {
+            row.push(Box::new(Icon { texture: create_error_icon() }));
+        }
+        row.push(Box::new(Column { children: text }));
+        let content: Vec<Box<dyn Widget>> = vec![
+            Box::new(Row { children: row }),
+            Box::new(Centered { child: Box::new(Button::new(button_text, action)) }),
+        ];
+        VintageWindow::new(id, title, content, sound)
+    }
 
-        if self.visible {
+    pub fn set_title(&mut self, title: &str) {
+        self.title = String::from(title);
+        self.relayout();
+    }
 
-            if mouse_x as u32 >=(draw_x as u32 + top_left_x) 
-                && mouse_y as u32 >=(draw_y as u32 + top_left_y) 
-                && mouse_x as u32 <= (draw_x as u32 + top_left_x + button_width)
-                && mouse_y as u32 <= (draw_y as u32 + top_left_y + button_height) 
-                && is_mouse_button_pressed(MouseButton::Left)
-            {
-                self.visible = !self.visible;
-                *quitting = Quitter::Yes;
-            }
+    pub fn set_content(&mut self, content: Vec<Box<dyn Widget>>) {
+        self.content = content;
+        self.relayout();
+    }
 
-            if mouse_x as u32 >= (draw_x as u32 + x0 as u32)
-                && mouse_y as u32 >= (draw_y as u32 + y0 as u32)
-                && mouse_x as u32 <= (draw_x as u32 + x0 as u32 + side_len)
-                && mouse_y as u32 <= (draw_y as u32 + y0 as u32 + side_len)
-                && is_mouse_button_pressed(MouseButton::Left) 
-            {
-                self.visible = false;
-            }
+    // whatever's in the window's text box, if it has one
+    pub fn text_value(&self) -> Option<String> {
+        self.content.iter().find_map(|widget| widget.text_value())
+    }
 
-            
-        }
+    // call after changing the content so the window fits it again
+    pub fn relayout(&mut self) {
+        let content = column_size(&self.content);
+        let title = text_size(&self.title, FONT_SIZE).x + CLOSE_SIZE * 2. + PADDING * 3.;
+        self.width = (content.x + PADDING * 2.).max(title).max(self.min_width);
+        self.height = content.y + TITLE_HEIGHT + PADDING * 2.;
+    }
 
-        if is_key_pressed(KeyCode::Escape) {
-            if self.visible == false {
-                play_sound_once(&self.sounds[0]);
-            }
-            self.visible = !self.visible;
-        }
+    pub fn rect(&self) -> Rect {
+        let (x, y) = match self.position {
+            Some(position) => (position.x, position.y),
+            None => (screen_width() / 2. - self.width / 2., screen_height() / 2. - self.height / 2.),
+        };
+        // always leave the title bar somewhere you can grab it
+        let x = x.min(screen_width() - 40.).max(40. - self.width);
+        let y = y.min(screen_height() - TASKBAR_HEIGHT - TITLE_HEIGHT).max(0.);
+        Rect::new(x.floor(), y.floor(), self.width, self.height)
     }
 
-    pub fn draw(
-        &mut self,
-    ) {
-       if self.visible {
-            let draw_x = screen_width() / 2. - (self.width as f32 / 2.); 
-            let draw_y = screen_height() / 2. - (self.height as f32 / 2.);
-
-            draw_texture_ex(
-                &self.texture,
-                draw_x,
-                draw_y,
-                WHITE,
-                DrawTextureParams {
-                    ..Default::default()
-                }
-            );
-            
-            let font_size = self.height as f32 * 0.12;
-
-            let button_width = (self.width as f32 * 0.28) as u32;
-            let button_height = (self.height as f32 * 0.15) as u32;
-            let top_left_x = (self.width as f32 * 0.5) - (button_width / 2) as f32;
-            let top_left_y = (self.height as f32 * 0.7) as u32 as f32;
-
-            draw_text_ex(
-                self.window_name.as_str(),
-                draw_x + (self.width as f32 * 0.015),
-                draw_y + (self.height as f32 * 0.11),
-                TextParams{
-                    font_size: font_size as u16,
-                    ..Default::default()
-                }
-            );
-
-            draw_text_ex(
-                self.button_text.as_str(),
-                draw_x + top_left_x + (button_width as f32 * 0.37),
-                draw_y + top_left_y + (button_height as f32 * 0.01 + font_size * 0.9),
-                TextParams{
-                    font_size: font_size as u16,
-                    color: BLACK,
-                    ..Default::default()
-                }
-            );
-
-
-            draw_text_ex(
-                self.message_text.as_str(),
-                draw_x + (self.width as f32 * 0.3),
-                draw_y + (self.height as f32 * 0.5),
-                TextParams{
-                    font_size: font_size as u16,
-                    color: BLACK,
-                    ..Default::default()
-                }
-            );
-
-            draw_text_ex(
-                self.line_two.as_str(),
-                draw_x + (self.width as f32 * 0.3) + (self.width as f32 * 0.1),
-                draw_y + (self.height as f32 * 0.5) + (font_size),
-                TextParams{
-                    font_size: font_size as u16,
-                    color: BLACK,
-                    ..Default::default()
-                }
-            );
-        } 
+    fn title_bar(&self) -> Rect {
+        let rect = self.rect();
+        Rect::new(rect.x, rect.y, rect.w, TITLE_HEIGHT)
     }
-}
 
-pub fn create_vintage_window_texture(width: u16, height: u16, window_type: &WindowType) -> Texture2D {
+    fn close_button(&self) -> Rect {
+        let rect = self.rect();
+        Rect::new(rect.x + rect.w - CLOSE_SIZE - 5., rect.y + (TITLE_HEIGHT - CLOSE_SIZE) / 2. + 1., CLOSE_SIZE, CLOSE_SIZE - 2.)
+    }
 
-    let gray_color: Color = Color {
-        r: 0.83921,
-        g: 0.82745,
-        b: 0.80784,
-        a: 1.0,
-    };
-    
-    let mut base_img_texture = Image::gen_image_color(width, height, gray_color);
-
-    draw_base_window(width as u32, height as u32, &mut base_img_texture);
-    // add type-specific graphic
-    match window_type {
-        WindowType::Error => {
-            draw_red_x(width as u32, height as u32, &mut base_img_texture);
-            draw_interactable_button(width as u32, height as u32, &mut base_img_texture);
-        },
-        WindowType::Alert => {
-
-        },
+    fn minimize_button(&self) -> Rect {
+        let close = self.close_button();
+        Rect::new(close.x - CLOSE_SIZE - 2., close.y, close.w, close.h)
     }
-    
-    draw_close_button(width as u32, height as u32, &mut base_img_texture);
 
-    Texture2D::from_image(&base_img_texture)
+    fn content_rects(&self) -> Vec<Rect> {
+        let rect = self.rect();
+        layout_column(&self.content, rect.x + PADDING, rect.y + TITLE_HEIGHT + PADDING, rect.w - PADDING * 2.)
+    }
 }
 
-fn draw_base_window(width: u32, height: u32, image: &mut Image) {
-
-    let clear_color: Color = Color {
-        r: 0.,
-        g: 0.,
-        b: 0.,
-        a: 0.,
-    };
-
-    let blue_color: Color = Color {
-        r: 0.031372,
-        g: 0.141176,
-        b: 0.419607,
-        a: 1.0,
-    };
+impl UiElement for VintageWindow {
+    fn id(&self) -> &'static str {
+        self.id
+    }
 
-    for w in 0..width {
-        for h in 0..height {
-//        if ((w  as f32) < width as f32 * 0.01) || (w as f32 >= width as f32 - (width as f32 * 0.01)) {
-//                image.set_pixel(w as u32, h as u32, blue_color);
-//            }
-        if (h as f32) < height as f32 * 0.15 || h as f32 > height as f32 - (width as f32 * 0.01) {
-                image.set_pixel(w as u32, h as u32, blue_color);
+    fn update(&mut self, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        if !self.is_visible() {
+            return false;
+        }
+        if let Some(grab) = self.drag_from {
+            if input.down {
+                self.position = Some(input.mouse - grab);
+                return true;
             }
+            // snap to whatever rect() clamped it to, so that's
+            // what gets remembered
+            let rect = self.rect();
+            self.position = Some(vec2(rect.x, rect.y));
+            self.drag_from = None;
+            actions.push(UiAction::WindowMoved(self.id));
+            return true;
         }
-
-    } 
-/*
-    // round off left corner
-    let r = width as f32 * 0.02;
-    for w in 0..r as i32 {
-        for h in 0..r as i32 {
-            let dx = w as f32 - r;
-            let dy = h as f32 - r;
-
-            let d2 = (dx * dx) + (dy * dy);
-            if d2 > (r * r) {
-               image.set_pixel(w as u32, h as u32, clear_color);
+        if input.pressed {
+            if self.close_button().contains(input.mouse) {
+                self.visible = false;
+                actions.push(UiAction::CloseWindow(self.id));
+                return true;
             }
+            if self.minimize_button().contains(input.mouse) {
+                self.minimized = true;
+                return true;
+            }
+            if self.title_bar().contains(input.mouse) {
+                let rect = self.rect();
+                self.drag_from = Some(input.mouse - vec2(rect.x, rect.y));
+                return true;
+            }
+        }
+        let rects = self.content_rects();
+        let mut consumed = false;
+        for (widget, rect) in self.content.iter_mut().zip(rects) {
+            consumed |= widget.update(rect, input, actions);
         }
+        consumed || self.contains(input.mouse.x, input.mouse.y)
     }
 
-    // round off right corner
-    for w in ((width as f32 - r) as i32)..width as i32 {
-        for h in 0..r as i32 {
-            let dx = (w as f32 - (width as f32 - r));
-            let dy = h as f32 - r;
+    fn draw(&self) {
+        if !self.is_visible() {
+            return;
+        }
+        let rect = self.rect();
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, WIN98_FACE);
+        draw_bevel(rect, true);
+        draw_rectangle(rect.x + 3., rect.y + 3., rect.w - 6., TITLE_HEIGHT - 3., WIN98_TITLE);
+        draw_ui_text(&self.title, rect.x + 7., rect.y + 5., FONT_SIZE, WHITE);
+
+        let close = self.close_button();
+        draw_rectangle(close.x, close.y, close.w, close.h, WIN98_FACE);
+        draw_bevel(close, true);
+        let (cx, cy) = (close.x + close.w / 2., close.y + close.h / 2.);
+        draw_line(cx - 4., cy - 3., cx + 4., cy + 4., 2., WIN98_TEXT);
+        draw_line(cx - 4., cy + 4., cx + 4., cy - 3., 2., WIN98_TEXT);
+
+        let minimize = self.minimize_button();
+        draw_rectangle(minimize.x, minimize.y, minimize.w, minimize.h, WIN98_FACE);
+        draw_bevel(minimize, true);
+        draw_rectangle(minimize.x + 4., minimize.y + minimize.h - 5., 6., 2., WIN98_TEXT);
+
+        for (widget, r) in self.content.iter().zip(self.content_rects()) {
+            widget.draw(r);
+        }
+    }
 
-            let d2 = (dx * dx) + (dy * dy);
-            if d2 > (r * r) {
-                image.set_pixel(w as u32, h as u32, clear_color);
+    fn is_visible(&self) -> bool {
+        self.visible && !self.minimized
+    }
+
+    fn set_visible(&mut self, visible: bool) {
+        if visible && !self.visible {
+            if let Some(sound) = &self.sound {
+                play_sound_once(sound);
             }
         }
+        self.visible = visible;
+        if !visible {
+            self.minimized = false;
+        }
     }
-*/
-}
-
-fn draw_interactable_button(width: u32, height: u32, image: &mut Image) {
-    let line_color: Color = Color { 
-        r: 0.121568,
-        g: 0.121568,
-        b: 0.121568,
-        a: 1.0,
-    };
-    
-    let button_width = (width as f32 * 0.28) as u32;
-    let button_height = (height as f32 * 0.15) as u32;
-    let top_left_x = (width as f32 * 0.5) as u32 - (button_width / 2);
-    let top_left_y = (height as f32 * 0.7) as u32;
-
-    draw_image_line(top_left_x, top_left_y, top_left_x + button_width, top_left_y, 3, image, line_color);
-    draw_image_line(top_left_x, top_left_y, top_left_x, top_left_y + button_height, 3, image, line_color);
-    draw_image_line(top_left_x, top_left_y + button_height, top_left_x + button_width, top_left_y + button_height, 3, image, line_color);
-    draw_image_line(top_left_x + button_width, top_left_y, top_left_x + button_width, top_left_y + button_height, 3, image, line_color);
-}
 
-fn draw_close_button(width: u32, height: u32, image: &mut Image) {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        self.is_visible() && self.rect().contains(vec2(x, y))
+    }
 
-    let red_color: Color = Color {
-        r: 0.99607,
-        g: 0.0,
-        b: 0.00784,
-        a: 1.0,
-    };
+    fn wants_keyboard(&self) -> bool {
+        self.is_visible() && self.content.iter().any(|widget| widget.wants_keyboard())
+    }
 
-    let gray_color: Color = Color {
-        r: 0.83921,
-        g: 0.82745,
-        b: 0.80784,
-        a: 1.0,
-    };
- 
-    // top left corner of the square
-    let (x0, y0) = (width as f32 - width as f32 * 0.077, height as f32 * 0.025);
-
-    let side_len = (width as f32 - width as f32 * 0.01) - (width as f32 - width as f32 * 0.07);
-    
-    // fill in red
-    for w in (x0 as u32)..=(x0 as u32 + side_len as u32) {
-        for h in (y0 as u32)..=(y0 as u32 + side_len as u32) {
-            image.set_pixel(w, h, gray_color);
-        }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
     }
-    let (p1, p2, p3, p4) = (x0 as u32 + (side_len * 0.25) as u32, y0 as u32 + (side_len * 0.25) as u32, x0 as u32 + side_len as u32 - (side_len * 0.25) as u32, y0 as u32 + side_len as u32 - (side_len * 0.25) as u32);
-    // draw 'X'
-    draw_image_line(p1, p2, p3, p4, 1, image, BLACK);
-    draw_image_line(p1, p4, p3, p2, 1, image, BLACK);
-
-    
-    // outline in black
-    for w in (x0 as u32)..=(x0 as u32 + side_len as u32) {
-        for h in (y0 as u32)..=(y0 as u32 + side_len as u32) {
-            if w == x0 as u32 || w == x0 as u32 + side_len as u32 {
-                image.set_pixel(w, h, BLACK);
-            }
-            if h == y0 as u32 || h == y0 as u32 + side_len as u32 {
-                image.set_pixel(w, h, BLACK);
-            }
-        }
+
+    fn title(&self) -> &str {
+        &self.title
     }
 
-    // left and top need this color:
-    let highlight_col: Color = Color {
-        r: 1.0,
-        g: 0.99215,
-        b: 0.97254,
-        a: 1.0,
-    };
+    fn is_open(&self) -> bool {
+        self.visible
+    }
 
-    for w in 0..3 {
-        for h in 0..height {
-            image.set_pixel(w, h, highlight_col);
-        }
+    fn is_minimized(&self) -> bool {
+        self.visible && self.minimized
     }
 
-    for w in 0..width {
-        for h in 0..3 {
-            image.set_pixel(w, h, highlight_col);
-        }
+    fn set_minimized(&mut self, minimized: bool) {
+        self.minimized = minimized;
+        self.drag_from = None;
     }
 
-    // add this semi-shadow:
-    let semi_shadow: Color = Color {
-        r: 0.80392,
-        g: 0.80392,
-        b: 0.77254,
-        a: 1.0,
-    };
-    for w in 0..width {
-        image.set_pixel(w, 1, semi_shadow);
+    fn position(&self) -> Option<Vec2> {
+        self.position
     }
-    for h in 0..height {
-        image.set_pixel(1, h, semi_shadow);
+
+    fn set_position(&mut self, position: Vec2) {
+        self.position = Some(position);
     }
+}
 
-    // bottom and right need this color:
-    let shadow_col: Color = Color {
-        r: 0.25490,
-        g: 0.25038,
-        b: 0.266666,
-        a: 1.0,
-    };
+pub const SETTINGS_WINDOW: &str = "settings";
 
-    for w in 0..width {
-        for h in height -3 .. height {
-            image.set_pixel(w, h, shadow_col);
-        }
-    }
+// Same window from the main menu and in game. Changes go out as
+// actions, whoever owns the settings applies and saves them.
+pub fn settings_window(settings: &Settings) -> VintageWindow {
+    VintageWindow::new(SETTINGS_WINDOW, "Settings", settings_content(settings), None)
+}
 
-    for w in width -3 .. width {
-        for h in 0..height {
-            image.set_pixel(w, h, shadow_col);
-        }
+// Pops the settings window up, refilled from `settings` in case
+// they changed some other way (C cycles the camera mode) since
+// it was last open.
+pub fn show_settings_window(ui: &mut UiStack, settings: &Settings) {
+    if let Some(window) = ui.get_mut(SETTINGS_WINDOW).and_then(|e| e.as_any_mut().downcast_mut::<VintageWindow>()) {
+        window.set_content(settings_content(settings));
     }
+    ui.show(SETTINGS_WINDOW);
+}
 
+fn settings_content(settings: &Settings) -> Vec<Box<dyn Widget>> {
+    let mut modes = ListBox::new(
+        CameraMode::ALL.iter().map(|mode| String::from(mode.name())).collect(),
+        CameraMode::ALL.len(),
+        Some(|i| UiAction::SetCameraMode(CameraMode::ALL[i])),
+    );
+    modes.selected = CameraMode::ALL.iter().position(|mode| *mode == settings.camera_mode);
+    let mut autosave = ListBox::new(
+        AUTOSAVE_CHOICES.iter()
+            .map(|minutes| match minutes {
+                0 => String::from("off"),
+                1 => String::from("every minute"),
+                _ => format!("every {} minutes", minutes),
+            })
+            .collect(),
+        AUTOSAVE_CHOICES.len(),
+        Some(|i| UiAction::SetAutosaveMinutes(AUTOSAVE_CHOICES[i])),
+    );
+    autosave.selected = AUTOSAVE_CHOICES.iter().position(|minutes| *minutes == settings.autosave_minutes);
+    vec![
+        Box::new(Label::new("Camera mode:")),
+        Box::new(modes),
+        Box::new(Label::new("Autosave:")),
+        Box::new(autosave),
+        Box::new(Centered { child: Box::new(Button::new("OK", UiAction::CloseWindow(SETTINGS_WINDOW))) }),
+    ]
+}
+
+// The red circle + white X from the old error window, cut out
+// on its own so it can go in an `Icon`.
+fn create_error_icon() -> Texture2D {
+    // draw_red_x puts a circle of radius 0.15 * height at
+    // (0.15 * width, 0.5 * height)
+    let (width, height) = (200, 200);
+    let mut image = Image::gen_image_color(width, height, Color::new(0., 0., 0., 0.));
+    draw_red_x(width as u32, height as u32, &mut image);
+    let r = height as f32 * 0.15;
+    let icon = image.sub_image(Rect::new(width as f32 * 0.15 - r, height as f32 * 0.5 - r, r * 2., r * 2.));
+    Texture2D::from_image(&icon)
 }
 
 fn draw_red_x(width: u32, height: u32, image: &mut Image) {
@@ -384,58 +336,22 @@ fn draw_red_x(width: u32, height: u32, image: &mut Image) {
     };
 
 
-    let (circle_x, circle_y) = (width as f32 * 0.15, height as f32 * 0.5);
+    let center = vec2(width as f32 * 0.15, height as f32 * 0.5);
     let r = height as f32 * 0.15;
+    let mut canvas = Canvas::new(image);
 
-    for w in (circle_x - r) as i32..(circle_x + r) as i32 {
-        for h in (circle_y - r) as i32..(circle_y + r) as i32 {
-            let dx = circle_x - w as f32;
-            let dy = circle_y - h as f32;
-            let d = ((dx * dx) + (dy * dy)).sqrt();
-
-            if d < r {
-                image.set_pixel(w as u32, h as u32, red_color);
-            }
-        } 
-        
-    }
+    canvas.fill(&Shape::Circle { center, radius: r }, red_color);
 
-    let (p1x, p1y) = (
-        circle_x as f64 + r as f64 * (std::f64::consts::PI / 4.).cos(),
-        circle_y as f64 + r as f64 * (std::f64::consts::PI / 4.).sin()
-    );
-
-    let (p2x, p2y) = (
-        circle_x as f64 + r as f64 * (std::f64::consts::PI * 3. / 4.).cos(),
-        circle_y as f64 + r as f64 * (std::f64::consts::PI * 3. / 4.).sin()
-    );
-
-    let (p3x, p3y) = (
-        circle_x as f64 + r as f64 * (std::f64::consts::PI * 5. / 4.).cos(),
-        circle_y as f64 + r as f64 * (std::f64::consts::PI * 5. / 4.).sin()
-    );
-
-    let (p4x, p4y) = (
-        circle_x as f64 + r as f64 * (std::f64::consts::PI * 7. / 4.).cos(),
-        circle_y as f64 + r as f64 * (std::f64::consts::PI * 7. / 4.).sin()
-    );
-
-    let thickness = (r * 0.11) as u8;
-
-    // Draw the first line of the white 'X'
-    draw_image_line(p1x as u32, p1y as u32, p3x as u32, p3y as u32, thickness, image, WHITE);
-
-    // Draw the second line of the 'X'
-    draw_image_line(p2x as u32, p2y as u32, p4x as u32, p4y as u32, thickness, image, WHITE);
-    for w in (circle_x - r) as i32..(circle_x + r) as i32 {
-        for h in (circle_y - r) as i32..(circle_y + r) as i32 {
-            let dx = circle_x - w as f32;
-            let dy = circle_y - h as f32;
-            let d = ((dx * dx) + (dy * dy)).sqrt();
+    // the white 'X', corner to corner across the circle. pulled in
+    // a bit so the round ends don't poke out past it
+    let width = r * 0.22;
+    let corner = |eighths: f32| {
+        let angle = std::f32::consts::PI * eighths / 4.;
+        center + vec2(angle.cos(), angle.sin()) * (r - width * 0.5)
+    };
+    canvas.fill(&Shape::Line { from: corner(1.), to: corner(5.), width }, WHITE);
+    canvas.fill(&Shape::Line { from: corner(3.), to: corner(7.), width }, WHITE);
 
-            if d < r && d > r - (r * 0.09){
-                image.set_pixel(w as u32, h as u32, BLACK); 
-            }
-        } 
-    }
+    // black ring round the inside of the edge
+    canvas.stroke(&Shape::Circle { center, radius: r * 0.955 }, r * 0.09, BLACK);
 }
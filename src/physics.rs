@@ -1,18 +1,50 @@
 pub type ForceVector = (f32, f32);
 
+// the "gravitational constant" of the universe. Not even
+// slightly realistic, it's just what makes things look right.
+pub const G: f32 = 0.000000001;
+
 use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use rayon::prelude::*;
 use crossbeam::thread;
 use super::camera::*;
 use super::star::*;
 use super::rockybody::*;
 use super::mathtools::*;
+use super::render::*;
+
+// Every body gets its own id when it's made. A merge keeps the id
+// of the bigger of the two, so anything keeping track of that one
+// carries on with the merged body. 0 is for things in the bodies
+// list that aren't really bodies.
+pub type BodyId = u64;
+
+static NEXT_BODY_ID: AtomicU64 = AtomicU64::new(1);
 
+pub fn next_body_id() -> BodyId {
+    NEXT_BODY_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+// Where the body with this id is in `bodies` right now. Hang on to
+// ids rather than indices, those shift every time bodies merge.
+pub fn body_index(bodies: &[Box<dyn PhysObj>], id: BodyId) -> Option<usize> {
+    bodies.iter().position(|body| body.id() == id)
+}
+
+// Just simulation state. Textures and anything else GPU side live
+// in the `RenderCache`, which finds them by `id` and makes them
+// from `appearance`.
 pub trait PhysObj: Send + Sync {
     
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 
+    fn id(&self) -> BodyId;
+    // None for things that don't get drawn as a body
+    fn appearance(&self) -> Option<Appearance>;
+
     fn xpos(&self) -> f32;
     fn ypos(&self) -> f32;
     fn xvel(&self) -> f32;
@@ -20,11 +52,18 @@ pub trait PhysObj: Send + Sync {
     fn mass(&self) -> u64;
     fn radius(&self) -> f32;
     fn force_vectors(&self) -> Vec<ForceVector>;
+    // steps by however long it's been since the last update
+    // (wall clock). The game runs off this.
     fn update(&mut self);
+    // steps by exactly `dt` seconds, no clocks involved. The
+    // headless sim runs off this so runs are repeatable.
+    fn step(&mut self, dt: f32);
+    // forget how long it's been since the last update, so
+    // the next one doesn't make up for time spent paused
+    fn reset_clock(&mut self);
     fn update_xvel(&mut self, update_val: f32);
     fn update_yvel(&mut self, update_val: f32);
     fn add_vector(&mut self, force_vec: ForceVector);
-    fn draw(&mut self, camera: &ZCamera);
     fn update_xpos(&mut self, update_val: f32);
     fn update_ypos(&mut self, update_val: f32);
 }
@@ -61,16 +100,38 @@ pub fn calculate_gravity<T: PhysObj>(body1: &T, body2: &T) -> ForceVector {
 }
 */
 pub fn calculate_gravity(body1: &dyn PhysObj, body2: &dyn PhysObj) -> ForceVector {
-    let (x0, y0) = (body1.xpos(), body1.ypos());
-    let (x1, y1) = (body2.xpos(), body2.ypos());
-    let (m0, m1) = (body1.mass() as f32, body2.mass() as f32);
+    gravity_between(&GravitySnapshot::of(body1), &GravitySnapshot::of(body2))
+}
+
+// Everything gravity needs to know about a body. Taken for every body
+// up front so the parallel pass can read them all while nothing moves.
+#[derive(Clone, Copy)]
+pub struct GravitySnapshot {
+    pub x: f32,
+    pub y: f32,
+    pub mass: f32,
+}
+
+impl GravitySnapshot {
+    pub fn of(body: &dyn PhysObj) -> GravitySnapshot {
+        GravitySnapshot {
+            x: body.xpos(),
+            y: body.ypos(),
+            mass: body.mass() as f32,
+        }
+    }
+}
+
+fn gravity_between(body1: &GravitySnapshot, body2: &GravitySnapshot) -> ForceVector {
+    let (x0, y0) = (body1.x, body1.y);
+    let (x1, y1) = (body2.x, body2.y);
+    let (m0, m1) = (body1.mass, body2.mass);
     
     let dx = x1 - x0;
     let dy = y1 - y0;
     let theta = f32::atan2(dy, dx);
     let r2 = (dx*dx + dy*dy);
-    let g = 0.000000001;
-    let f = g * (m0 * m1) / r2;
+    let f = G * (m0 * m1) / r2;
 
 
     // these are the x,y components of the
@@ -89,21 +150,9 @@ pub fn calculate_gravity(body1: &dyn PhysObj, body2: &dyn PhysObj) -> ForceVecto
 
 }
 
-/*
-pub fn update_gravity_physics(bodies: &mut Vec<Box<dyn PhysObj>>) {
-    bodies.par_iter_mut().enumerate().for_each(|(i, body_i)| {
-        let bodies_slice = bodies.split_at_mut(i + 1).1;
-        bodies_slice.iter_mut().enumerate().for_each(|(j, body_j)| {
-            if j > 0 { // Skip the first element since it's the same as body_i
-                let (fx, fy) = calculate_gravity(&**body_i, &**body_j);
-                body_i.add_vector((fx, fy));
-                body_j.add_vector((-fx, -fy));
-            }
-        });
-    });
-}
-*/
-pub fn update_gravity_physics(
+// Every pair pulls on each other, one pair at a time. Kept around
+// as the reference the parallel version has to match.
+pub fn update_gravity_physics_serial(
     bodies: &mut Vec<Box<dyn PhysObj>>,
 ) {
     for i in 0..bodies.len() {
@@ -114,7 +163,175 @@ pub fn update_gravity_physics(
         }
     }
 }
-pub async fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
+
+// Every pair pulls on each other. Goes wide when there's more than
+// one thread to go wide on; with just the one, working each pair out
+// twice makes the parallel version a good bit slower than the loop.
+pub fn update_gravity_physics(
+    bodies: &mut Vec<Box<dyn PhysObj>>,
+) {
+    if rayon::current_num_threads() > 1 {
+        update_gravity_physics_parallel(bodies);
+    } else {
+        update_gravity_physics_serial(bodies);
+    }
+}
+
+// Same thing spread over every core. The old `par_iter_mut` try
+// couldn't work since each pair writes to both bodies, so instead
+// every body works out its own pulls from a snapshot nobody's
+// writing to, and they all get handed over afterwards.
+//
+// Each pair gets worked out twice (once from each end), but the
+// numbers are exactly the serial ones: same `gravity_between` with
+// the bodies the same way round, and each body gets its pulls in
+// the same order, one vector at a time (they're summed in `step`,
+// and summing them up front would round differently).
+pub fn update_gravity_physics_parallel(
+    bodies: &mut Vec<Box<dyn PhysObj>>,
+) {
+    let snapshot: Vec<GravitySnapshot> = bodies.iter().map(|body| GravitySnapshot::of(body.as_ref())).collect();
+    let pulls: Vec<Vec<ForceVector>> = (0..snapshot.len()).into_par_iter().map(|k| {
+        let mut pulls = Vec::with_capacity(snapshot.len().saturating_sub(1));
+        // the serial loop hands `k` the other end of (i, k) first...
+        for i in 0..k {
+            let (fx, fy) = gravity_between(&snapshot[i], &snapshot[k]);
+            pulls.push((-fx, -fy));
+        }
+        // ...then its own end of (k, j)
+        for j in k+1..snapshot.len() {
+            pulls.push(gravity_between(&snapshot[k], &snapshot[j]));
+        }
+        pulls
+    }).collect();
+    bodies.par_iter_mut().zip(pulls).for_each(|(body, pulls)| {
+        for pull in pulls {
+            body.add_vector(pull);
+        }
+    });
+}
+
+// Steps every body at once. Bodies only touch themselves in `step`,
+// so this comes out the same as going through them in order.
+pub fn step_bodies(bodies: &mut [Box<dyn PhysObj>], dt: f32) {
+    bodies.par_iter_mut().for_each(|body| body.step(dt));
+}
+
+// Same for the wall clock `update`. Don't hand this the player, it
+// reads the keyboard, which has to be done from the main thread.
+pub fn update_bodies(bodies: &mut [Box<dyn PhysObj>]) {
+    bodies.par_iter_mut().for_each(|body| body.update());
+}
+
+// The shape of `body`'s orbit around `central`, treating it as
+// a plain two body problem (so it's only a snapshot, the real
+// orbit drifts as everything tugs on everything else).
+pub struct OrbitalElements {
+    pub semi_major: f32,
+    pub semi_minor: f32,
+    pub eccentricity: f32,
+    // direction of periapsis, radians from +x
+    pub arg_periapsis: f32,
+    // seconds per orbit. infinite if it isn't bound
+    pub period: f32,
+    pub bound: bool,
+}
+
+pub fn orbital_elements(body: &dyn PhysObj, central: &dyn PhysObj) -> OrbitalElements {
+    // f32 falls apart on the star masses, so do this in f64
+    let mu = G as f64 * (central.mass() as f64 + body.mass() as f64);
+    let (rx, ry) = ((body.xpos() - central.xpos()) as f64, (body.ypos() - central.ypos()) as f64);
+    let (vx, vy) = ((body.xvel() - central.xvel()) as f64, (body.yvel() - central.yvel()) as f64);
+    let r = (rx * rx + ry * ry).sqrt().max(f64::EPSILON);
+    let v2 = vx * vx + vy * vy;
+
+    let energy = v2 / 2. - mu / r;
+    let r_dot_v = rx * vx + ry * vy;
+    let ex = ((v2 - mu / r) * rx - r_dot_v * vx) / mu;
+    let ey = ((v2 - mu / r) * ry - r_dot_v * vy) / mu;
+    let eccentricity = (ex * ex + ey * ey).sqrt();
+
+    let bound = energy < 0. && eccentricity < 1.;
+    let (semi_major, semi_minor, period) = if bound {
+        let a = -mu / (2. * energy);
+        (a, a * (1. - eccentricity * eccentricity).sqrt(), 2. * std::f64::consts::PI * (a * a * a / mu).sqrt())
+    } else {
+        (f64::INFINITY, f64::INFINITY, f64::INFINITY)
+    };
+
+    OrbitalElements {
+        semi_major: semi_major as f32,
+        semi_minor: semi_minor as f32,
+        eccentricity: eccentricity as f32,
+        arg_periapsis: ey.atan2(ex) as f32,
+        period: period as f32,
+        bound,
+    }
+}
+
+// Index of the heaviest body, which is what everything
+// else in a system is (mostly) orbiting
+pub fn heaviest_body(bodies: &[Box<dyn PhysObj>]) -> Option<usize> {
+    (0..bodies.len()).max_by_key(|&i| bodies[i].mass())
+}
+
+// Buckets bodies into square cells so "what's near this point"
+// doesn't have to look at all 450 of them. Rebuilt from scratch
+// every update, it's cheap and bodies move around a lot anyway.
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> SpatialGrid {
+        SpatialGrid {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell_of(&self, x: f32, y: f32) -> (i32, i32) {
+        ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32)
+    }
+
+    // a body goes in every cell its circle overlaps
+    pub fn rebuild(&mut self, bodies: &[Box<dyn PhysObj>]) {
+        self.cells.clear();
+        for (i, body) in bodies.iter().enumerate() {
+            let r = body.radius();
+            let (x0, y0) = self.cell_of(body.xpos() - r, body.ypos() - r);
+            let (x1, y1) = self.cell_of(body.xpos() + r, body.ypos() + r);
+            for cx in x0..=x1 {
+                for cy in y0..=y1 {
+                    self.cells.entry((cx, cy)).or_default().push(i);
+                }
+            }
+        }
+    }
+
+    // indices of every body that might be within `r` of (x, y).
+    // "might", the caller still has to check the distance
+    pub fn query(&self, x: f32, y: f32, r: f32) -> Vec<usize> {
+        let (x0, y0) = self.cell_of(x - r, y - r);
+        let (x1, y1) = self.cell_of(x + r, y + r);
+        let mut found = Vec::new();
+        for cx in x0..=x1 {
+            for cy in y0..=y1 {
+                if let Some(cell) = self.cells.get(&(cx, cy)) {
+                    for &i in cell.iter() {
+                        if !found.contains(&i) {
+                            found.push(i);
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+pub fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
     for i in 0..bodies.len() {
         for j in i+1..bodies.len() {
             let dx = bodies[j].xpos() - bodies[i].xpos();
@@ -149,8 +366,9 @@ pub async fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
                                 bigger_star.xvel(),
                                 bigger_star.yvel(),
                                 new_body_mass,
-                                r
-                            ).await
+                                r,
+                                sub_seed(bigger_star.seed(), smaller_star.seed()),
+                            ).with_id(bigger_star.id())
                         );
                         bodies.remove(i);
                         bodies.remove(j - 1);
@@ -191,8 +409,9 @@ pub async fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
                             star.xvel(),
                             star.yvel(),
                             new_body_mass,
-                            r
-                        ).await
+                            r,
+                            sub_seed(star.seed(), rocky_body.seed()),
+                        ).with_id(star.id())
                     );
                     let empty_body: Box<dyn PhysObj> = Box::new(
                         RockyBody::new(
@@ -202,7 +421,8 @@ pub async fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
                             0.,
                             0,
                             0.,
-                        ).await
+                            0,
+                        )
                     );
                     bodies.remove(j);
                     bodies.insert(j, empty_body);
@@ -238,8 +458,9 @@ pub async fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
                                 bigger_body.xvel(),
                                 bigger_body.yvel(),
                                 new_body_mass,
-                                r
-                            ).await
+                                r,
+                                sub_seed(bigger_body.seed(), smaller_body.seed()),
+                            ).with_id(bigger_body.id())
                         );
                         let empty_body: Box<dyn PhysObj> = Box::new(
                             RockyBody::new(
@@ -249,7 +470,8 @@ pub async fn check_collisions(bodies: &mut Vec<Box<dyn PhysObj>>) {
                                 0.,
                                 0,
                                 0.,
-                            ).await
+                                0,
+                            )
                         );
                         bodies.remove(j);
                         bodies.insert(j, empty_body);
@@ -348,3 +570,59 @@ fn fast_root(n :f32) -> f32 {
     1. / fast_inverse_sqrt(n)
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::rand::{Rng, SeedableRng};
+    use ::rand::rngs::StdRng;
+    use crate::ships::PlayerShip;
+
+    // plain bodies that just integrate whatever they're pushed with
+    fn scattered_bodies(seed: u64, n: usize) -> Vec<Box<dyn PhysObj>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| Box::new(PlayerShip::new(
+                rng.gen_range(-20000. ..20000.),
+                rng.gen_range(-20000. ..20000.),
+                rng.gen_range(-5. ..5.),
+                rng.gen_range(-5. ..5.),
+                rng.gen_range(10000000..10000000000000000),
+                10.,
+            )) as Box<dyn PhysObj>)
+            .collect()
+    }
+
+    fn bits(bodies: &[Box<dyn PhysObj>]) -> Vec<[u32; 4]> {
+        bodies.iter()
+            .map(|body| [body.xpos().to_bits(), body.ypos().to_bits(), body.xvel().to_bits(), body.yvel().to_bits()])
+            .collect()
+    }
+
+    #[test]
+    fn parallel_gravity_matches_serial() {
+        let mut serial = scattered_bodies(42, 120);
+        let mut parallel = scattered_bodies(42, 120);
+        // more than one thread even on a one core machine, so the
+        // work really does get split up
+        let pool = rayon::ThreadPoolBuilder::new().num_threads(4).build().unwrap();
+
+        for step in 0..20 {
+            update_gravity_physics_serial(&mut serial);
+            pool.install(|| update_gravity_physics_parallel(&mut parallel));
+            for (a, b) in serial.iter().zip(parallel.iter()) {
+                let (a, b) = (a.force_vectors(), b.force_vectors());
+                assert_eq!(a.len(), b.len());
+                for (fa, fb) in a.iter().zip(b.iter()) {
+                    assert!(fa.0.to_bits() == fb.0.to_bits() && fa.1.to_bits() == fb.1.to_bits(), "step {}: {:?} vs {:?}", step, fa, fb);
+                }
+            }
+
+            for body in serial.iter_mut() {
+                body.step(0.5);
+            }
+            pool.install(|| step_bodies(&mut parallel, 0.5));
+            assert_eq!(bits(&serial), bits(&parallel), "step {}", step);
+        }
+    }
+}
@@ -2,9 +2,9 @@ use std::any::Any;
 use macroquad::prelude::*;
 use std::time::*;
 
-use super::ships::*;
 use super::physics::*;
-use super::camera::*;
+use super::render::*;
+use super::shipgen::*;
 
 
 pub struct Player {
@@ -15,8 +15,8 @@ pub struct Player {
     mass: u64,
     radius: f32,
     force_vecs: Vec<ForceVector>,
+    id: BodyId,
     last_update: Instant,
-    texture: Texture2D,
 }
 
 impl Player {
@@ -36,8 +36,8 @@ impl Player {
             mass,
             radius,
             force_vecs,
+            id: next_body_id(),
             last_update: Instant::now(),
-            texture: create_ship_texture(radius),
         }
     }
 
@@ -60,6 +60,16 @@ impl Player {
         }
     }
 
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.xpos = x;
+        self.ypos = y;
+    }
+
+    pub fn set_velocity(&mut self, xvel: f32, yvel: f32) {
+        self.xvel = xvel;
+        self.yvel = yvel;
+    }
+
     pub fn clone(&self) -> Player {
         Player {
             xpos: self.xpos,
@@ -69,8 +79,8 @@ impl Player {
             mass: self.mass,
             radius: self.radius,
             force_vecs: self.force_vecs.clone(),
+            id: self.id,
             last_update: self.last_update,
-            texture: self.texture.clone(),
         }
     }
 }
@@ -82,6 +92,10 @@ impl PhysObj for Player {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+    fn id(&self) -> BodyId { self.id }
+    fn appearance(&self) -> Option<Appearance> {
+        Some(Appearance::Ship { radius: self.radius, seed: PLAYER_SHIP_SEED, class: ShipClass::Player })
+    }
     fn xpos(&self) -> f32 { self.xpos }
     fn ypos(&self) -> f32 { self.ypos }
     fn xvel(&self) -> f32 { self.xvel }
@@ -112,12 +126,21 @@ impl PhysObj for Player {
         self.force_vecs.push(force_vec);
     }
 
+    fn reset_clock(&mut self) {
+        self.last_update = Instant::now();
+    }
+
     fn update(&mut self) {
         
         let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update);
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.step(dt);
+        self.update_ship_velocity();
+    }
 
-        
+    // integrates whatever forces have piled up over `dt` seconds
+    fn step(&mut self, dt: f32) {
         let mut final_vector: ForceVector = (0., 0.);
         //assume this self as a force_vectors full of vectors
         for i in 0..self.force_vecs.len() {
@@ -128,45 +151,12 @@ impl PhysObj for Player {
 
         let ax = final_vector.0 / self.mass as f32;
         let ay = final_vector.1 / self.mass as f32;
-        
-        // v = at
-        self.xvel += ax * elapsed.as_secs_f32(); 
-        self.yvel += ay * elapsed.as_secs_f32();
 
-        self.xpos += self.xvel * elapsed.as_secs_f32();
-        self.ypos += self.yvel * elapsed.as_secs_f32();
-        
-        self.last_update = now;
-        
-        self.update_ship_velocity();
-    }
+        // v = at
+        self.xvel += ax * dt;
+        self.yvel += ay * dt;
 
-    fn draw(&mut self, camera: &ZCamera) {
-        let draw_x = (self.xpos as f64 - camera.xpos as f64) * camera.zoom - 150. * camera.zoom;
-        let draw_y = (self.ypos as f64 - camera.ypos as f64) * camera.zoom - 150. * camera.zoom;
-        
-        draw_texture_ex(
-            &self.texture,
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2(300. * camera.zoom as f32, 300. as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        );
+        self.xpos += self.xvel * dt;
+        self.ypos += self.yvel * dt;
     }
 }
-
-pub fn follow_ship(body: &mut Box<dyn PhysObj>, camera: &mut ZCamera) {
-//    let lerp_factor = 0.6; // Adjust lerp_factor as needed for smooth transitions
-
-    // Get half the screen width and height in pixels
-    let half_width_pixels = screen_width() / 2.0;
-    let half_height_pixels = screen_height() / 2.0;
-
-    camera.xpos = -(half_width_pixels / camera.zoom as f32) + body.xpos();
-    camera.ypos = -(half_height_pixels / camera.zoom as f32) + body.ypos(); 
-}
-
-
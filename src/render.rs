@@ -0,0 +1,550 @@
+use std::collections::{HashMap, HashSet};
+use std::thread;
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use macroquad::prelude::*;
+use macroquad::miniquad::{BlendFactor, BlendState, BlendValue, Equation};
+use rayon::prelude::*;
+
+use super::physics::*;
+use super::star::*;
+use super::rockybody::*;
+use super::shipgen::*;
+use super::camera::*;
+use super::texturetools::*;
+use super::loading::*;
+use super::star_system::*;
+
+// Bodies this close in size / heat share textures. Nobody can tell
+// a 40.2 radius planet from a 40.4 one, and merges nudge both a bit
+// every time.
+const RADIUS_STEP: f32 = 0.5;
+const TEMPERATURE_STEP: f32 = 100.;
+
+// Roughly how much texture memory the cache holds on to before it
+// starts throwing out whatever's gone longest without being drawn.
+// One star is sixteen 1000x1000 frames, ~64MB, so this is a dozen or so.
+const CACHE_BUDGET_BYTES: usize = 1024 * 1024 * 1024;
+
+// how far a planet's atmosphere sticks out, as a multiple of its radius
+const ATMOSPHERE: f32 = 1.3;
+
+// Draws a planet map (see `create_rocky_body_maps`) as a sphere seen
+// from straight on. Every pixel on the disc works out where on the
+// globe it's looking at, then reads the ground and clouds there, each
+// slid round by their own rotation (in turns, 0..1).
+const PLANET_VERTEX: &str = r#"#version 100
+attribute vec3 position;
+attribute vec2 texcoord;
+attribute vec4 color0;
+
+varying lowp vec2 uv;
+varying lowp vec4 color;
+
+uniform mat4 Model;
+uniform mat4 Projection;
+
+void main() {
+    gl_Position = Projection * Model * vec4(position, 1);
+    color = color0 / 255.0;
+    uv = texcoord;
+}"#;
+
+const PLANET_FRAGMENT: &str = r#"#version 100
+precision mediump float;
+
+varying vec2 uv;
+varying vec4 color;
+
+uniform sampler2D Texture;
+uniform float rotation;
+uniform float cloud_rotation;
+
+const float PI = 3.14159265;
+// has to match ATMOSPHERE
+const float ATMOSPHERE = 1.3;
+
+void main() {
+    // -1..1 is the planet, out to ATMOSPHERE is sky
+    vec2 p = (uv * 2.0 - 1.0) * ATMOSPHERE;
+    float d = length(p);
+    if (d > 1.0) {
+        float q = (d - 1.0) / (ATMOSPHERE - 1.0);
+        float glow = 1.0 - clamp(q, 0.0, 1.0);
+        gl_FragColor = vec4(0.7, 0.8, 1.0, 0.35 * glow * glow) * color;
+        return;
+    }
+    float z = sqrt(1.0 - d * d);
+    float lon = atan(p.x, z) / (2.0 * PI);
+    // keep off the very edges of each half, or the filtering
+    // bleeds the clouds into the ground and back
+    float lat = clamp(asin(p.y) / PI + 0.5, 0.01, 0.99);
+    vec4 ground = texture2D(Texture, vec2(fract(lon + rotation), lat * 0.5));
+    vec4 clouds = texture2D(Texture, vec2(fract(lon + cloud_rotation), 0.5 + lat * 0.5));
+    vec3 surface = mix(ground.rgb, clouds.rgb, clouds.a);
+    // a bit darker round the edge so it reads as round
+    float shade = 0.55 + 0.45 * z;
+    gl_FragColor = vec4(surface * shade, 1.0) * color;
+}"#;
+
+// What a body looks like, as far as making its textures goes.
+// Bodies hand one of these out and the render cache turns it
+// into pixels, so nothing on the simulation side touches GL.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Appearance {
+    Star { radius: f32, temperature: f32, seed: u64 },
+    Rocky { radius: f32, seed: u64, spin: f32 },
+    Ship { radius: f32, seed: u64, class: ShipClass },
+}
+
+impl Appearance {
+    pub fn key(&self) -> TextureKey {
+        match *self {
+            Appearance::Star { radius, temperature, seed } => TextureKey {
+                kind: BodyKind::Star,
+                seed,
+                radius: quantize(radius, RADIUS_STEP),
+                temperature: quantize(temperature, TEMPERATURE_STEP),
+                class: None,
+            },
+            // spin doesn't change the map, only how it's drawn
+            Appearance::Rocky { radius, seed, .. } => TextureKey {
+                kind: BodyKind::Rocky,
+                seed,
+                radius: quantize(radius, RADIUS_STEP),
+                temperature: 0,
+                class: None,
+            },
+            Appearance::Ship { radius, seed, class } => TextureKey {
+                kind: BodyKind::Ship,
+                seed,
+                radius: quantize(radius, RADIUS_STEP),
+                temperature: 0,
+                class: Some(class),
+            },
+        }
+    }
+
+    // what it's drawn as when it's too small to bother texturing
+    // (or its textures aren't made yet, then `images` is empty)
+    pub fn lod_color(&self, images: &[Image]) -> Color {
+        match *self {
+            Appearance::Star { temperature, .. } => star_lod_color(temperature),
+            Appearance::Rocky { .. } | Appearance::Ship { .. } => images.first().map_or(GRAY, average_color),
+        }
+    }
+
+    // smallest that dot gets, in pixels
+    fn min_dot_pixels(&self) -> f32 {
+        match self {
+            Appearance::Rocky { .. } => 1.,
+            _ => 1.5,
+        }
+    }
+}
+
+fn quantize(value: f32, step: f32) -> u32 {
+    (value / step).round().max(0.) as u32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BodyKind {
+    Star,
+    Rocky,
+    Ship,
+}
+
+// Everything that goes into making a body's textures, rounded off so
+// near enough identical bodies land on the same key. Two bodies with
+// the same key get the exact same pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureKey {
+    pub kind: BodyKind,
+    pub seed: u64,
+    // in RADIUS_STEPs
+    pub radius: u32,
+    // in TEMPERATURE_STEPs, 0 for anything that isn't a star
+    pub temperature: u32,
+    // None for anything that isn't a ship
+    pub class: Option<ShipClass>,
+}
+
+impl TextureKey {
+    pub fn radius(&self) -> f32 {
+        self.radius as f32 * RADIUS_STEP
+    }
+
+    pub fn temperature(&self) -> f32 {
+        self.temperature as f32 * TEMPERATURE_STEP
+    }
+
+    // Every frame for this key, made on the CPU. Slow, but doesn't
+    // need a window, so it's fine off the main thread.
+    pub fn images(&self) -> Vec<Image> {
+        match self.kind {
+            BodyKind::Star => gen_star_images(self.radius(), self.temperature(), self.seed),
+            BodyKind::Rocky => vec![create_rocky_body_maps(self.radius(), self.seed)],
+            BodyKind::Ship => vec![create_ship_image(self.radius(), self.seed, self.class.unwrap_or(ShipClass::Fighter))],
+        }
+    }
+}
+
+// Every key's images at once, as many bodies at a time as there are
+// cores (and each of those a row at a time across them too)
+pub fn generate_images(keys: &[TextureKey]) -> Vec<Vec<Image>> {
+    keys.par_iter().map(|key| key.images()).collect()
+}
+
+// Throws textures out of `textures` until `bytes` (what they all
+// add up to) is under `budget`, and returns what's left. Least
+// recently drawn goes first. Anything drawn last frame stays even
+// if that leaves us over budget, otherwise a big enough system
+// would regenerate its textures every frame.
+fn evict_lru(textures: &mut HashMap<TextureKey, BodyTextures>, mut bytes: usize, budget: usize, frame: u64) -> usize {
+    while bytes > budget {
+        let oldest = textures.iter()
+            .filter(|(_, textures)| textures.last_used + 1 < frame)
+            .min_by_key(|(_, textures)| textures.last_used)
+            .map(|(key, _)| *key);
+        match oldest {
+            Some(key) => {
+                if let Some(textures) = textures.remove(&key) {
+                    bytes -= textures.bytes;
+                }
+            },
+            None => break,
+        }
+    }
+    bytes
+}
+
+pub struct BodyTextures {
+    pub frames: Vec<Texture2D>,
+    // None means "ask the appearance", the dot color of a star
+    // doesn't depend on its pixels
+    pub average_color: Option<Color>,
+    // how big it's drawn, in world units
+    pub size: Vec2,
+    pub bytes: usize,
+    // frame number it was last drawn on, for the LRU
+    last_used: u64,
+}
+
+impl BodyTextures {
+    pub fn from_images(key: TextureKey, images: &[Image]) -> BodyTextures {
+        let size = match key.kind {
+            // a map, not a picture of the planet. the shader draws the
+            // planet out to 1.0 and its atmosphere out to ATMOSPHERE
+            BodyKind::Rocky => Vec2::splat(key.radius() * 2. * ATMOSPHERE),
+            // drawn finer than the rest, see SHIP_TEXELS
+            BodyKind::Ship => images.first().map_or(Vec2::ZERO, |image| vec2(image.width as f32, image.height as f32) / SHIP_TEXELS),
+            // everything else is one texel per world unit
+            _ => images.first().map_or(Vec2::ZERO, |image| vec2(image.width as f32, image.height as f32)),
+        };
+        let average_color = match key.kind {
+            BodyKind::Rocky | BodyKind::Ship => images.first().map(average_color),
+            _ => None,
+        };
+        BodyTextures {
+            frames: images.iter().map(Texture2D::from_image).collect(),
+            average_color,
+            size,
+            bytes: images.iter().map(|image| image.bytes.len()).sum(),
+            last_used: 0,
+        }
+    }
+
+    fn lod_color(&self, appearance: &Appearance) -> Color {
+        self.average_color.unwrap_or_else(|| appearance.lod_color(&[]))
+    }
+}
+
+// The GPU side of the bodies. Textures are keyed by what they were
+// made from rather than by body, so identical bodies share them, and
+// anything not made yet gets made on a worker thread while the body
+// carries on with whatever it looked like before (or a dot).
+pub struct RenderCache {
+    textures: HashMap<TextureKey, BodyTextures>,
+    // what each body was last drawn with, so a body whose key changed
+    // (a merge) keeps showing that until the new one's ready
+    shown: HashMap<BodyId, TextureKey>,
+    // sent off to the workers, not back yet
+    pending: HashSet<TextureKey>,
+    jobs: Sender<TextureKey>,
+    done: Receiver<(TextureKey, Vec<Image>)>,
+    // wraps planet maps round a sphere. None if the shader didn't
+    // compile, planets are just dots then
+    planet_material: Option<Material>,
+    frame: u64,
+    bytes: usize,
+}
+
+impl RenderCache {
+    // no Default, this starts worker threads and that shouldn't
+    // happen behind something as innocent looking as `default()`
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> RenderCache {
+        let (jobs, job_queue) = unbounded::<TextureKey>();
+        let (finished, done) = unbounded();
+        // leave a core for the game itself
+        let workers = thread::available_parallelism().map_or(2, |n| n.get().saturating_sub(1).max(1));
+        for i in 0..workers {
+            let job_queue = job_queue.clone();
+            let finished = finished.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("texture-worker-{}", i))
+                .spawn(move || {
+                    // both ends go away with the cache, and so do we
+                    for key in job_queue.iter() {
+                        if finished.send((key, key.images())).is_err() {
+                            break;
+                        }
+                    }
+                });
+            if let Err(e) = spawned {
+                println!("WARNING: couldn't start texture worker: {}", e);
+            }
+        }
+        let planet_material = load_material(
+            ShaderSource::Glsl { vertex: PLANET_VERTEX, fragment: PLANET_FRAGMENT },
+            MaterialParams {
+                uniforms: vec![
+                    UniformDesc::new("rotation", UniformType::Float1),
+                    UniformDesc::new("cloud_rotation", UniformType::Float1),
+                ],
+                pipeline_params: PipelineParams {
+                    color_blend: Some(BlendState::new(
+                        Equation::Add,
+                        BlendFactor::Value(BlendValue::SourceAlpha),
+                        BlendFactor::OneMinusValue(BlendValue::SourceAlpha),
+                    )),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+        let planet_material = match planet_material {
+            Ok(material) => Some(material),
+            Err(e) => {
+                println!("WARNING: couldn't build the planet shader, planets will be dots: {:?}", e);
+                None
+            },
+        };
+        RenderCache {
+            textures: HashMap::new(),
+            shown: HashMap::new(),
+            pending: HashSet::new(),
+            jobs,
+            done,
+            planet_material,
+            frame: 0,
+            bytes: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    // Once a frame before drawing: uploads whatever the workers have
+    // finished and throws out old textures if we're over budget
+    pub fn update(&mut self) {
+        self.frame += 1;
+        while let Ok((key, images)) = self.done.try_recv() {
+            self.pending.remove(&key);
+            self.insert(key, &images);
+        }
+        self.evict();
+    }
+
+    fn insert(&mut self, key: TextureKey, images: &[Image]) {
+        let mut textures = BodyTextures::from_images(key, images);
+        textures.last_used = self.frame;
+        self.bytes += textures.bytes;
+        if let Some(old) = self.textures.insert(key, textures) {
+            self.bytes -= old.bytes;
+        }
+    }
+
+    fn evict(&mut self) {
+        self.bytes = evict_lru(&mut self.textures, self.bytes, CACHE_BUDGET_BYTES, self.frame);
+    }
+
+    fn request(&mut self, key: TextureKey) {
+        if self.textures.contains_key(&key) || !self.pending.insert(key) {
+            return;
+        }
+        if self.jobs.send(key).is_err() {
+            // no workers, make it here instead
+            self.pending.remove(&key);
+            self.insert(key, &key.images());
+        }
+    }
+
+    // Makes everything in `system` up front behind a loading screen,
+    // rather than popping in a bit at a time once it's on screen.
+    // Forgets what the last system's bodies were showing too.
+    pub async fn prepare(&mut self, system: &StarSystem, loading: &mut Loading) {
+        self.shown.clear();
+        let mut missing: Vec<TextureKey> = Vec::new();
+        for body in system.bodies.iter() {
+            if let Some(appearance) = body.appearance() {
+                let key = appearance.key();
+                if !self.textures.contains_key(&key) && !missing.contains(&key) {
+                    missing.push(key);
+                }
+                self.shown.insert(body.id(), key);
+            }
+        }
+        loading.stage("Painting surfaces");
+        loading.expect(missing.len());
+        // a few bodies per core at a time, so the loading screen
+        // still gets a look in between
+        let batch = rayon::current_num_threads() * 2;
+        for keys in missing.chunks(batch) {
+            for (key, images) in keys.iter().zip(generate_images(keys)) {
+                self.insert(*key, &images);
+                loading.tick().await;
+            }
+        }
+        self.evict();
+    }
+
+    // drops what bodies that aren't in `bodies` any more were showing
+    // (merged into something else, or in another system). Their
+    // textures stay cached until the LRU gets to them.
+    pub fn forget_missing(&mut self, bodies: &[Box<dyn PhysObj>]) {
+        let live: HashSet<BodyId> = bodies.iter().map(|body| body.id()).collect();
+        self.shown.retain(|id, _| live.contains(id));
+    }
+
+    // how many bodies we're keeping track of
+    pub fn shown(&self) -> usize {
+        self.shown.len()
+    }
+
+    pub fn draw_body(&mut self, body: &dyn PhysObj, camera: &ZCamera, time: f64) {
+        let appearance = match body.appearance() {
+            Some(appearance) => appearance,
+            None => return,
+        };
+        let (id, x, y, radius) = (body.id(), body.xpos(), body.ypos(), body.radius());
+        let key = appearance.key();
+        // too small to be more than a dot, so don't go making
+        // textures for it until somebody zooms in
+        let lod = camera.use_lod(radius);
+        let key = if self.textures.contains_key(&key) {
+            self.shown.insert(id, key);
+            Some(key)
+        } else {
+            if !lod {
+                self.request(key);
+            }
+            self.shown.get(&id).copied().filter(|old| self.textures.contains_key(old))
+        };
+        let textures = match key.and_then(|key| self.textures.get_mut(&key)) {
+            Some(textures) => textures,
+            None => {
+                camera.draw_world_dot(x, y, radius, appearance.min_dot_pixels(), appearance.lod_color(&[]));
+                return;
+            },
+        };
+        textures.last_used = self.frame;
+        if lod || textures.frames.is_empty() {
+            camera.draw_world_dot(x, y, radius, appearance.min_dot_pixels(), textures.lod_color(&appearance));
+            return;
+        }
+        if let Appearance::Rocky { spin, .. } = appearance {
+            let material = match &self.planet_material {
+                Some(material) => material,
+                None => {
+                    camera.draw_world_dot(x, y, radius, appearance.min_dot_pixels(), textures.lod_color(&appearance));
+                    return;
+                },
+            };
+            let turns = time * spin as f64;
+            material.set_uniform("rotation", turns.fract() as f32);
+            material.set_uniform("cloud_rotation", (turns * CLOUD_DRIFT).fract() as f32);
+            gl_use_material(material);
+            camera.draw_world_texture(&textures.frames[0], x, y, textures.size);
+            gl_use_default_material();
+            return;
+        }
+        // offset by id so every star isn't pulsing in step
+        let frame = ((time * STAR_FPS) as usize + id as usize) % textures.frames.len();
+        camera.draw_world_texture(&textures.frames[frame], x, y, textures.size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star(radius: f32, temperature: f32) -> TextureKey {
+        Appearance::Star { radius, temperature, seed: 7 }.key()
+    }
+
+    #[test]
+    fn near_identical_bodies_share_a_key() {
+        assert_eq!(star(40.2, 5820.), star(40.1, 5790.));
+        assert_ne!(star(40., 5800.), star(41., 5800.));
+        assert_ne!(star(40., 5800.), star(40., 5900.));
+        assert_eq!(star(40.2, 5820.).radius(), 40.);
+        assert_eq!(star(40.2, 5820.).temperature(), 5800.);
+
+        // spin only changes how a planet's drawn
+        let rocky = |spin| Appearance::Rocky { radius: 30., seed: 7, spin }.key();
+        assert_eq!(rocky(0.1), rocky(-0.3));
+        assert_eq!(rocky(0.1).temperature, 0);
+        assert_ne!(rocky(0.1), Appearance::Rocky { radius: 30., seed: 8, spin: 0.1 }.key());
+
+        let ship = |class| Appearance::Ship { radius: 5., seed: 7, class }.key();
+        assert_ne!(ship(ShipClass::Fighter), ship(ShipClass::Freighter));
+        assert_ne!(ship(ShipClass::Fighter).kind, rocky(0.1).kind);
+    }
+
+    fn cached(bytes: usize, last_used: u64) -> BodyTextures {
+        BodyTextures {
+            frames: Vec::new(),
+            average_color: None,
+            size: Vec2::ZERO,
+            bytes,
+            last_used,
+        }
+    }
+
+    #[test]
+    fn evicts_least_recently_drawn_first() {
+        let key = |seed| Appearance::Rocky { radius: 30., seed, spin: 0. }.key();
+        let mut textures: HashMap<TextureKey, BodyTextures> = HashMap::new();
+        for (seed, last_used) in [(1, 5), (2, 2), (3, 8), (4, 9)] {
+            textures.insert(key(seed), cached(100, last_used));
+        }
+
+        // over by one texture, the one drawn longest ago goes
+        let bytes = evict_lru(&mut textures, 400, 300, 10);
+        assert_eq!(bytes, 300);
+        assert!(!textures.contains_key(&key(2)));
+
+        let bytes = evict_lru(&mut textures, bytes, 100, 10);
+        assert_eq!(bytes, 100);
+        assert!(!textures.contains_key(&key(1)));
+        assert!(!textures.contains_key(&key(3)));
+        assert!(textures.contains_key(&key(4)));
+
+        // drawn last frame, so it stays even over budget
+        assert_eq!(evict_lru(&mut textures, bytes, 0, 10), 100);
+        assert!(textures.contains_key(&key(4)));
+    }
+}
@@ -1,17 +1,17 @@
 use macroquad::prelude::*;
-use std::time::{SystemTime, Instant, Duration};
+use std::time::Instant;
 use std::any::Any;
-use ::rand::Rng;
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
 use noise::{NoiseFn, Perlin};
 use ::rand::distributions::{Distribution, Uniform};
 
 use super::physics::*;
-use super::camera::*;
-use super::texturetools::*;
+use super::loading::*;
+use super::render::*;
 use super::mathtools::*;
+use super::texturetools::*;
 
-const WIDTH: u32 = 290;
-const HEIGHT: u32 = 290;
 
 pub struct RockyBody {
     xpos: f32,
@@ -20,11 +20,13 @@ pub struct RockyBody {
     yvel: f32,
     mass: u64,
     radius: f32,
+    seed: u64,
+    // turns a second, negative goes the other way. comes out of the
+    // seed, so it doesn't need saving
+    spin: f32,
     force_vectors: Vec<ForceVector>,
-    frames: Vec<Texture2D>,
-    frame_idx: usize,
+    id: BodyId,
     last_update: Instant,
-    last_frame_update: Instant,
 }
 
 impl PhysObj for RockyBody {
@@ -35,6 +37,11 @@ impl PhysObj for RockyBody {
         self
     }
 
+    fn id(&self) -> BodyId { self.id }
+    fn appearance(&self) -> Option<Appearance> {
+        Some(Appearance::Rocky { radius: self.radius, seed: self.seed, spin: self.spin })
+    }
+
     fn xpos(&self) -> f32 { self.xpos }
 
     fn ypos(&self) -> f32 { self.ypos }
@@ -63,19 +70,19 @@ impl PhysObj for RockyBody {
         self.ypos += update_val;
     }
 
+    fn reset_clock(&mut self) {
+        self.last_update = Instant::now();
+    }
+
     fn update(&mut self) {
         let now = Instant::now();
-        let elapsed_frame = now.duration_since(self.last_frame_update);
-        // update the frame to display 7x per second
-        if elapsed_frame >= Duration::from_secs_f32(1. / 7.) {
-            self.last_frame_update = now;
-            if self.frame_idx < self.frames.len() - 1  {
-                self.frame_idx += 1;
-            } else {
-                self.frame_idx = 0;
-            }
-        }
-        let elapsed = now.duration_since(self.last_update);
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.step(dt);
+    }
+
+    // integrates whatever forces have piled up over `dt` seconds
+    fn step(&mut self, dt: f32) {
         let mut final_vector: ForceVector = (0., 0.);
         //assume this self as a force_vectors full of vectors
         for i in 0..self.force_vectors.len() {
@@ -86,11 +93,10 @@ impl PhysObj for RockyBody {
         let ax = final_vector.0 / self.mass as f32;
         let ay = final_vector.1 / self.mass as f32;
         // v = at
-        self.xvel += ax * elapsed.as_secs_f32(); 
-        self.yvel += ay * elapsed.as_secs_f32();
-        self.xpos += self.xvel * elapsed.as_secs_f32();
-        self.ypos += self.yvel * elapsed.as_secs_f32();
-        self.last_update = now;
+        self.xvel += ax * dt;
+        self.yvel += ay * dt;
+        self.xpos += self.xvel * dt;
+        self.ypos += self.yvel * dt;
     }
 
     fn add_vector(&mut self, force_vec: ForceVector) {
@@ -100,60 +106,59 @@ impl PhysObj for RockyBody {
     fn force_vectors(&self) -> Vec<ForceVector> {
         self.force_vectors.clone()
     }
-
-    fn draw(
-        &mut self, 
-        camera: &ZCamera,
-    ) {
-        /*
-        * TODO
-        * I want to check and see if the bodyis within the bounds
-        * of the camera, and only draw it if it is. 
-        */
-        let draw_x = (self.xpos as f64 - (WIDTH as f64 / 2.) - camera.xpos as f64) * camera.zoom;
-        let draw_y = (self.ypos as f64 - (HEIGHT as f64 / 2.) - camera.ypos as f64) * camera.zoom;
-        //draw_texture(&self.frames[self.frame_idx], draw_x as f32 - (tex_x as f32 * scl_x as f32 / 2.), draw_y as f32 - (tex_y as f32 * scl_y as f32 / 2.), WHITE);
-        draw_texture_ex(
-            &self.frames[self.frame_idx],
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                //dest_size: Some(vec2(screen_width() * camera.zoom as f32, screen_height() * camera.zoom as f32)),
-                dest_size: Some(vec2(WIDTH as f32 * camera.zoom as f32, HEIGHT as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        )
-    }
 }
 
 impl RockyBody {
     
-    pub  async fn new(
+    pub fn new(
         xpos: f32,
         ypos: f32,
         xvel: f32,
         yvel: f32,
         mass: u64,
         radius: f32,
+        seed: u64,
     ) -> RockyBody {
-        let frames = gen_rand_rocky_body_textures(mass, radius).await;
         RockyBody {
             xpos: xpos,
             ypos: ypos,
             xvel,
             yvel,
-            frames,
             mass,
             radius,
+            seed,
+            spin: rocky_spin(seed),
             force_vectors: Vec::new(),
-            frame_idx: 0,
+            id: next_body_id(),
             last_update: Instant::now(),
-            last_frame_update: Instant::now(),
         }
     }
+
+    // same as `Star::with_id`, a merged body is drawn as the bigger
+    // of the two until its own texture's ready
+    pub fn with_id(mut self, id: BodyId) -> RockyBody {
+        self.id = id;
+        self
+    }
+
+    pub fn seed(&self) -> u64 { self.seed }
+
+    pub fn spin(&self) -> f32 { self.spin }
+}
+
+// somewhere between one turn every 15 and every 90 seconds, mostly
+// the same way round
+pub fn rocky_spin(seed: u64) -> f32 {
+    let mut rng = StdRng::seed_from_u64(sub_seed(seed, 3));
+    let turns: f32 = rng.gen_range((1. / 90.)..(1. / 15.));
+    if rng.gen_bool(0.85) { turns } else { -turns }
 }
 
+// clouds go round a bit faster than the ground under them
+pub const CLOUD_DRIFT: f64 = 1.25;
+
+pub const NUM_ROCKY_BODIES: usize = 450;
+
 pub async fn load_rocky_bodies(
     loaded: &mut bool,
     bodies: &mut Vec<Box<dyn PhysObj>>,
@@ -161,17 +166,20 @@ pub async fn load_rocky_bodies(
     win_height: f32,
     orbit_px: f32,
     orbit_py: f32,
-    m: u64
+    m: u64,
+    seed: u64,
+    loading: &mut Loading,
 ) {
-    let num_rocky_bodies = 450;
-    for _ in 0..num_rocky_bodies{
-        bodies.push(Box::new(gen_random_rocky_body(win_width, win_height, orbit_px, orbit_py, m).await));
+    loading.stage("Forming planets");
+    for i in 0..NUM_ROCKY_BODIES as u64 {
+        bodies.push(Box::new(gen_random_rocky_body(win_width, win_height, orbit_px, orbit_py, m, sub_seed(seed, i))));
+        loading.tick().await;
     }
     *loaded = true;
 }
 
-async fn gen_random_rocky_body(win_width: f32, win_height: f32, ox: f32, oy: f32, sm: u64) -> RockyBody {
-    let mut rng = ::rand::thread_rng();
+fn gen_random_rocky_body(win_width: f32, win_height: f32, ox: f32, oy: f32, sm: u64, seed: u64) -> RockyBody {
+    let mut rng = StdRng::seed_from_u64(seed);
     let mass = rng.gen_range(10000000..10000000000000000);
     let r = r_from_mass(mass as f32, (10000000., 10000000000000000.), (5., 90.));
     let win_width = win_width as i32;
@@ -187,8 +195,7 @@ async fn gen_random_rocky_body(win_width: f32, win_height: f32, ox: f32, oy: f32
     let d = ((dx * dx) + (dy * dy)).sqrt();
     let t_1 = f32::atan(dx / dy);
     let t_2 = right_angle - t_1;
-    let g = 0.000000001;
-    let vo = ((g * sm as f32) / d).sqrt();
+    let vo = ((G * sm as f32) / d).sqrt();
     let pixel_conversion = 1.;
     let mut x_component = f32::sin(t_2) * vo * pixel_conversion;
     let mut y_component = f32::cos(t_2) * vo * pixel_conversion;
@@ -208,75 +215,68 @@ async fn gen_random_rocky_body(win_width: f32, win_height: f32, ox: f32, oy: f32
         y_component,
         mass,
         r,
-    ).await
-}
-
-async fn gen_rand_rocky_body_textures(mass: u64, radius: f32) -> Vec<Texture2D> {
-    let mut textures: Vec<Texture2D> = Vec::new();
-    textures.push(create_rocky_body(mass, radius).await);
-    textures 
+        sub_seed(seed, 1),
+    )
 }
 
-async fn create_rocky_body(mass: u64, radius: f32) -> Texture2D {
-    let now = SystemTime::now();
-    let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH)
-        .expect("Time went backwards");
-    let seed = (
-        since_epoch.as_secs() * 1_000_000_000 + since_epoch
-            .subsec_nanos() as u64 
-    ) as u32;
+// Planets are drawn as a sphere by a shader (see render.rs) that wraps
+// a flat map round them, so turning one is just sliding its map along
+// and nothing has to be regenerated. This makes that map: longitude
+// across, latitude down, ground in the top half and clouds in the
+// bottom (the shader only gets the one texture).
+pub fn create_rocky_body_maps(radius: f32, seed: u64) -> Image {
+    let seed = perlin_seed(seed);
     let perlin = Perlin::new(seed);
-    let clear_color = Color{
-        r: 0.,
-        g: 0.,
-        b: 0.,
-        a: 0.,
-    };
-    let cloud_perlin = Perlin::new(seed + 1);
-    let (width, height) = (WIDTH as u16, HEIGHT as u16);
-    let (cx, cy) = (width as u16 / 2, height as u16 / 2);
-    let mut base_img_texture = Image::gen_image_color(width, height, clear_color);
-    let mut cloud_layer = Image::gen_image_color(width, height, clear_color);
-    // LAND LAYER
-    for w in 0..width {
-        for h in 0..height {
-            let dx = (w - cx);
-            let dy = (h - cy);
-            let d = (((dx * dx) + (dy * dy)) as f32).sqrt();
-
-            let p = radius + radius * 0.3;
-            let v = p - radius;
-
-
-            if d <= radius {
-                let val = perlin.get([w as f64 / 91., h as f64 / 92.]);
-                let val = (val + 1.0) / 2.0;
-                let mut color: Color = Color {
-                    r: 0.,
-                    g: 1. - val as f32,
-                    b: val as f32,
-                    a: 1.,
-                };
-                base_img_texture.set_pixel(w as u32, h as u32, color);
-
-            }
-
-            if d <= p {
-                let q = d - radius;
-                let mut cloud_val = cloud_perlin.get([w as f64 / 50., h as f64 / 20.]);
-                cloud_val = (cloud_val + 1.) / 2.;
-                let cloud_cover: Color = Color {
-                    r: 0.7 + 0.3 * cloud_val as f32,
-                    g: 0.7 + 0.3 * cloud_val as f32,
-                    b: 1.,
-                    a: (0.5 * cloud_val as f32) * (1. - q / v),
-                };
-                cloud_layer.set_pixel(w as u32, h as u32, cloud_cover)
-            } 
+    let cloud_perlin = Perlin::new(seed.wrapping_add(1));
+    // about one texel per world unit round the equator
+    let width = ((std::f32::consts::TAU * radius).ceil() as u16).clamp(32, 512) / 2 * 2;
+    let height = width / 2;
+    let r = radius as f64;
+
+    par_image(width, height * 2, |i, j| {
+        let clouds = j >= height as u32;
+        let j = if clouds { j - height as u32 } else { j };
+        // the point on the sphere this texel wraps onto, out at the
+        // planet's radius so the noise is the same scale as it always was
+        let lon = (i as f64 + 0.5) / width as f64 * std::f64::consts::TAU;
+        let lat = ((j as f64 + 0.5) / height as f64 - 0.5) * std::f64::consts::PI;
+        let (x, y, z) = (lat.cos() * lon.cos() * r, lat.sin() * r, lat.cos() * lon.sin() * r);
+
+        if !clouds {
+            // LAND LAYER
+            let val = perlin.get([x / 91., y / 92., z / 91.]);
+            let val = (val + 1.0) / 2.0;
+            Color { r: 0., g: 1. - val as f32, b: val as f32, a: 1. }.into()
+        } else {
+            // CLOUD LAYER, stretched out east-west into bands
+            let mut cloud_val = cloud_perlin.get([x / 50., y / 20., z / 50.]);
+            cloud_val = (cloud_val + 1.) / 2.;
+            Color {
+                r: 0.7 + 0.3 * cloud_val as f32,
+                g: 0.7 + 0.3 * cloud_val as f32,
+                b: 1.,
+                a: 0.5 * cloud_val as f32,
+            }.into()
         }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_maps() {
+        let a = create_rocky_body_maps(30., 42);
+        let b = create_rocky_body_maps(30., 42);
+        assert!(a.bytes == b.bytes);
     }
 
-    base_img_texture.overlay(&cloud_layer);
-    Texture2D::from_image(&base_img_texture)
+    #[test]
+    fn different_seed_different_maps() {
+        let a = create_rocky_body_maps(30., 42);
+        let b = create_rocky_body_maps(30., 43);
+        assert!(a.bytes != b.bytes);
+    }
 }
-
@@ -0,0 +1,774 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::{FromStr, SplitWhitespace};
+use std::time::SystemTime;
+
+use super::physics::*;
+use super::star::*;
+use super::rockybody::*;
+use super::ships::*;
+use super::player::*;
+use super::life::*;
+use super::camera::*;
+use super::galaxy::*;
+use super::star_system::*;
+use super::loading::*;
+use super::mathtools::*;
+
+// Bump this whenever the format changes, and add a step to
+// `migrate` that brings the previous version up to it.
+pub const SAVE_VERSION: u32 = 3;
+
+const SAVE_DIR: &str = "saves";
+
+// autosaves go round-robin through this many files, so there's
+// always a few older ones to fall back on
+pub const AUTOSAVE_SLOTS: usize = 3;
+
+pub fn save_dir() -> &'static Path {
+    Path::new(SAVE_DIR)
+}
+
+pub fn quicksave_path() -> PathBuf {
+    save_dir().join("quicksave.txt")
+}
+
+pub fn autosave_path(dir: &Path, slot: usize) -> PathBuf {
+    dir.join(format!("autosave_{}.txt", slot + 1))
+}
+
+// The slot in `dir` the next autosave should go in: the first one
+// that hasn't been used yet, otherwise whichever was written longest ago.
+pub fn next_autosave_slot(dir: &Path) -> usize {
+    let mut oldest: Option<(usize, SystemTime)> = None;
+    for slot in 0..AUTOSAVE_SLOTS {
+        let modified = match fs::metadata(autosave_path(dir, slot)).and_then(|m| m.modified()) {
+            Ok(modified) => modified,
+            Err(_) => return slot,
+        };
+        if oldest.is_none_or(|(_, time)| modified < time) {
+            oldest = Some((slot, modified));
+        }
+    }
+    oldest.map_or(0, |(slot, _)| slot)
+}
+
+// Everything in a save file, before any of it has been turned
+// back into bodies. Reading this is cheap, rebuilding the
+// textures in `restore` is the slow part.
+pub struct SaveFile {
+    pub version: u32,
+    // unix time it was written at (0 if we don't know)
+    pub saved_at: u64,
+    pub seed: u64,
+    // where the player was, for the save browser
+    pub system_name: String,
+    pub sim_time: f64,
+    pub active: usize,
+    pub destination: Option<usize>,
+    pub systems: Vec<SavedSystem>,
+}
+
+// One generated system. Systems nobody has been to aren't in the
+// save at all, the galaxy layout puts them back from the seed.
+pub struct SavedSystem {
+    pub index: usize,
+    pub seed: u64,
+    pub mass: u64,
+    pub radius: f32,
+    pub origin: (f32, f32),
+    pub camera: SavedCamera,
+    pub civ: SavedCiv,
+    pub bodies: Vec<SavedBody>,
+}
+
+pub struct SavedCamera {
+    pub xpos: f32,
+    pub ypos: f32,
+    pub zoom: f64,
+    pub target_zoom: f64,
+    pub mode: CameraMode,
+}
+
+pub struct SavedCiv {
+    pub energy_output: f64,
+    pub dexterity: f64,
+    pub strength: f64,
+    pub constitution: f64,
+    pub damage: f64,
+    pub size: f64,
+    pub seed: u64,
+    pub home_seed: Option<u64>,
+}
+
+// (x, y, xvel, yvel, mass, radius) comes first on every one
+pub enum SavedBody {
+    Star { x: f32, y: f32, xvel: f32, yvel: f32, mass: u64, radius: f32, seed: u64, temperature: f32 },
+    Rocky { x: f32, y: f32, xvel: f32, yvel: f32, mass: u64, radius: f32, seed: u64 },
+    Ship { x: f32, y: f32, xvel: f32, yvel: f32, mass: u64, radius: f32, desired_x: f32, desired_y: f32, max_dv: f32, seed: u64 },
+    Player { x: f32, y: f32, xvel: f32, yvel: f32, mass: u64, radius: f32 },
+}
+
+// Writes out every generated system in the galaxy. The file is
+// plain `key = value` lines like settings.txt, except the order
+// matters: everything after a `system = n` line belongs to that
+// system.
+pub fn save_game(path: &Path, galaxy: &Galaxy) -> Result<(), String> {
+    let mut contents = String::from("# PARA-GENESIS save\n");
+    contents += &format!("version = {}\n", SAVE_VERSION);
+    contents += &format!("saved_at = {}\n", unix_now());
+    contents += &format!("seed = {}\n", galaxy.seed);
+    contents += &format!("system_name = {}\n", galaxy.active_entry().name);
+    contents += &format!("sim_time = {}\n", galaxy.sim_time);
+    contents += &format!("active = {}\n", galaxy.active);
+    contents += &format!("destination = {}\n", galaxy.destination.map_or(String::from("-"), |d| d.to_string()));
+
+    for (i, entry) in galaxy.entries.iter().enumerate() {
+        let system = match entry.system.as_ref() {
+            Some(system) => system,
+            None => continue,
+        };
+        contents += &format!("\nsystem = {}\n", i);
+        contents += &format!("system_seed = {}\n", system.seed);
+        contents += &format!("mass = {}\n", system.mass);
+        contents += &format!("radius = {}\n", system.radius);
+        contents += &format!("origin = {} {}\n", system.origin.0, system.origin.1);
+        let camera = &system.camera;
+        contents += &format!(
+            "camera = {} {} {} {} {}\n",
+            camera.xpos, camera.ypos, camera.zoom, camera.target_zoom, camera.mode.name(),
+        );
+        let life = &system.life;
+        contents += &format!(
+            "civ = {} {} {} {} {} {} {} {}\n",
+            life.energy_output, life.dexterity, life.strength, life.constitution,
+            life.damage, life.size, life.seed,
+            life.home_seed.map_or(String::from("-"), |s| s.to_string()),
+        );
+        for body in system.bodies.iter() {
+            match save_body(body.as_ref()) {
+                Some(line) => contents += &format!("body = {}\n", line),
+                None => println!("WARNING: don't know how to save a body, leaving it out"),
+            }
+        }
+    }
+
+    write_atomic(path, &contents)
+}
+
+// Writes to a temp file next to `path` and renames it over the top
+// once it's all on disk. A crash halfway through leaves the temp
+// file behind, never a half written save.
+fn write_atomic(path: &Path, contents: &str) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("couldn't make {}: {}", dir.display(), e))?;
+    }
+    let tmp = path.with_extension("tmp");
+    let written = fs::File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("couldn't write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn save_body(body: &dyn PhysObj) -> Option<String> {
+    let common = format!(
+        "{} {} {} {} {} {}",
+        body.xpos(), body.ypos(), body.xvel(), body.yvel(), body.mass(), body.radius(),
+    );
+    let any = body.as_any();
+    if let Some(star) = any.downcast_ref::<Star>() {
+        return Some(format!("star {} {} {}", common, star.seed(), star.temperature()));
+    }
+    if let Some(rocky) = any.downcast_ref::<RockyBody>() {
+        return Some(format!("rocky {} {}", common, rocky.seed()));
+    }
+    if let Some(ship) = any.downcast_ref::<Ship>() {
+        return Some(format!("ship {} {} {} {} {}", common, ship.desired_x, ship.desired_y, ship.max_dv, ship.seed));
+    }
+    if any.downcast_ref::<Player>().is_some() {
+        return Some(format!("player {}", common));
+    }
+    None
+}
+
+// Reads and upgrades a save, without rebuilding anything yet
+pub fn read_save(path: &Path) -> Result<SaveFile, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+    read_save_text(&contents)
+}
+
+fn read_save_text(contents: &str) -> Result<SaveFile, String> {
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            lines.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let version = match lines.first() {
+        Some((key, value)) if key == "version" => value.parse::<u32>()
+            .map_err(|_| format!("bad version `{}`", value))?,
+        _ => return Err(String::from("not a save file (no version)")),
+    };
+    let lines = migrate(version, lines)?;
+    parse_save(&lines)
+}
+
+// Brings an older save up to `SAVE_VERSION` one version at a
+// time, so each step only has to know about the one before it.
+fn migrate(mut version: u32, mut lines: Vec<(String, String)>) -> Result<Vec<(String, String)>, String> {
+    if version > SAVE_VERSION {
+        return Err(format!("save is version {}, newer than this game ({})", version, SAVE_VERSION));
+    }
+    while version < SAVE_VERSION {
+        lines = match version {
+            1 => migrate_v1(lines),
+            2 => migrate_v2(lines),
+            _ => return Err(format!("no way to upgrade a version {} save", version)),
+        };
+        version += 1;
+    }
+    if let Some(first) = lines.first_mut() {
+        first.1 = SAVE_VERSION.to_string();
+    }
+    Ok(lines)
+}
+
+// v2 added `saved_at` and `system_name` for the save browser.
+// We can't know when an old save was made, but the name comes
+// straight back out of the galaxy layout.
+fn migrate_v1(mut lines: Vec<(String, String)>) -> Vec<(String, String)> {
+    let value = |key: &str| lines.iter().find(|(k, _)| k == key).and_then(|(_, v)| v.parse::<u64>().ok());
+    let name = match (value("seed"), value("active")) {
+        (Some(seed), Some(active)) => layout_galaxy(seed)
+            .get(active as usize)
+            .map_or(String::from("?"), |entry| entry.name.clone()),
+        _ => String::from("?"),
+    };
+    lines.insert(1, (String::from("saved_at"), String::from("0")));
+    lines.insert(2, (String::from("system_name"), name));
+    lines
+}
+
+// v3 gave ships a seed for their looks. Old ones get one made up
+// from the save's seed and where they are in the file, so the same
+// old save always loads with the same ships.
+fn migrate_v2(mut lines: Vec<(String, String)>) -> Vec<(String, String)> {
+    let seed = lines.iter().find(|(k, _)| k == "seed").and_then(|(_, v)| v.parse::<u64>().ok()).unwrap_or(0);
+    for (n, (key, value)) in lines.iter_mut().enumerate() {
+        if key == "body" && value.split_whitespace().next() == Some("ship") {
+            *value = format!("{} {}", value, sub_seed(seed, n as u64));
+        }
+    }
+    lines
+}
+
+fn parse_save(lines: &[(String, String)]) -> Result<SaveFile, String> {
+    let mut save = SaveFile {
+        version: SAVE_VERSION,
+        saved_at: 0,
+        seed: 0,
+        system_name: String::new(),
+        sim_time: 0.,
+        active: 0,
+        destination: None,
+        systems: Vec::new(),
+    };
+    let mut seen_seed = false;
+    for (n, (key, value)) in lines.iter().enumerate() {
+        let bad = |what: &str| format!("line {} ({}): {}", n + 1, key, what);
+        let mut fields = value.split_whitespace();
+        match key.as_str() {
+            "version" => {},
+            "saved_at" => save.saved_at = field(&mut fields).map_err(|e| bad(&e))?,
+            "system_name" => save.system_name = value.clone(),
+            "seed" => {
+                save.seed = field(&mut fields).map_err(|e| bad(&e))?;
+                seen_seed = true;
+            },
+            "sim_time" => save.sim_time = field(&mut fields).map_err(|e| bad(&e))?,
+            "active" => save.active = field(&mut fields).map_err(|e| bad(&e))?,
+            "destination" => save.destination = optional_field(&mut fields).map_err(|e| bad(&e))?,
+            "system" => save.systems.push(SavedSystem {
+                index: field(&mut fields).map_err(|e| bad(&e))?,
+                seed: 0,
+                mass: 0,
+                radius: 0.,
+                origin: (0., 0.),
+                camera: SavedCamera { xpos: 0., ypos: 0., zoom: 1., target_zoom: 1., mode: CameraMode::Follow },
+                civ: SavedCiv {
+                    energy_output: 0., dexterity: 0., strength: 0., constitution: 0.,
+                    damage: 0., size: 0., seed: 0, home_seed: None,
+                },
+                bodies: Vec::new(),
+            }),
+            _ => {
+                // everything else belongs to the last `system`
+                let system = save.systems.last_mut().ok_or_else(|| bad("outside of any system"))?;
+                parse_system_line(system, key, &mut fields).map_err(|e| bad(&e))?;
+            },
+        }
+    }
+    if !seen_seed {
+        return Err(String::from("save has no seed"));
+    }
+    if !save.systems.iter().any(|system| system.index == save.active) {
+        return Err(String::from("save doesn't have the system the player was in"));
+    }
+    Ok(save)
+}
+
+fn parse_system_line(system: &mut SavedSystem, key: &str, fields: &mut SplitWhitespace) -> Result<(), String> {
+    match key {
+        "system_seed" => system.seed = field(fields)?,
+        "mass" => system.mass = field(fields)?,
+        "radius" => system.radius = field(fields)?,
+        "origin" => system.origin = (field(fields)?, field(fields)?),
+        "camera" => {
+            system.camera = SavedCamera {
+                xpos: field(fields)?,
+                ypos: field(fields)?,
+                zoom: field(fields)?,
+                target_zoom: field(fields)?,
+                mode: {
+                    let name: String = field(fields)?;
+                    CameraMode::from_name(&name).ok_or_else(|| format!("unknown camera mode `{}`", name))?
+                },
+            };
+        },
+        "civ" => {
+            system.civ = SavedCiv {
+                energy_output: field(fields)?,
+                dexterity: field(fields)?,
+                strength: field(fields)?,
+                constitution: field(fields)?,
+                damage: field(fields)?,
+                size: field(fields)?,
+                seed: field(fields)?,
+                home_seed: optional_field(fields)?,
+            };
+        },
+        "body" => {
+            let kind: String = field(fields)?;
+            let (x, y, xvel, yvel, mass, radius) = (
+                field(fields)?, field(fields)?, field(fields)?, field(fields)?, field(fields)?, field(fields)?,
+            );
+            let body = match kind.as_str() {
+                "star" => SavedBody::Star { x, y, xvel, yvel, mass, radius, seed: field(fields)?, temperature: field(fields)? },
+                "rocky" => SavedBody::Rocky { x, y, xvel, yvel, mass, radius, seed: field(fields)? },
+                "ship" => SavedBody::Ship {
+                    x, y, xvel, yvel, mass, radius,
+                    desired_x: field(fields)?,
+                    desired_y: field(fields)?,
+                    max_dv: field(fields)?,
+                    seed: field(fields)?,
+                },
+                "player" => SavedBody::Player { x, y, xvel, yvel, mass, radius },
+                _ => return Err(format!("unknown body type `{}`", kind)),
+            };
+            system.bodies.push(body);
+        },
+        // from a newer minor change we don't care about
+        _ => println!("WARNING: ignoring unknown save key `{}`", key),
+    }
+    Ok(())
+}
+
+fn field<T: FromStr>(fields: &mut SplitWhitespace) -> Result<T, String> {
+    let text = fields.next().ok_or_else(|| String::from("missing value"))?;
+    text.parse::<T>().map_err(|_| format!("bad value `{}`", text))
+}
+
+// `-` means nothing
+fn optional_field<T: FromStr>(fields: &mut SplitWhitespace) -> Result<Option<T>, String> {
+    match fields.next() {
+        Some("-") => Ok(None),
+        Some(text) => text.parse::<T>().map(Some).map_err(|_| format!("bad value `{}`", text)),
+        None => Err(String::from("missing value")),
+    }
+}
+
+// Turns a save back into a galaxy. Textures aren't in the save,
+// every body regenerates its own from its seed (same as when it
+// was first made), which is why this gets a loading screen.
+pub async fn restore(save: &SaveFile, loading: &mut Loading) -> Result<Galaxy, String> {
+    let mut galaxy = Galaxy::new(save.seed);
+    if save.active >= galaxy.entries.len() {
+        return Err(format!("active system {} doesn't exist", save.active));
+    }
+    loading.expect(save.systems.iter().map(|system| system.bodies.len()).sum());
+
+    for saved in save.systems.iter() {
+        let entry = galaxy.entries.get(saved.index)
+            .ok_or_else(|| format!("system {} doesn't exist", saved.index))?;
+        loading.stage(&format!("Restoring {}", entry.name));
+        let mut bodies: Vec<Box<dyn PhysObj>> = Vec::with_capacity(saved.bodies.len());
+        for body in saved.bodies.iter() {
+            bodies.push(restore_body(body));
+            loading.tick().await;
+        }
+
+        let civ = &saved.civ;
+        let mut life = Civilization::new_rand(civ.seed);
+        life.energy_output = civ.energy_output;
+        life.dexterity = civ.dexterity;
+        life.strength = civ.strength;
+        life.constitution = civ.constitution;
+        life.damage = civ.damage;
+        life.size = civ.size;
+        life.home_seed = civ.home_seed;
+
+        let mut camera = ZCamera::new_origin();
+        camera.set_mode(saved.camera.mode);
+        camera.xpos = saved.camera.xpos;
+        camera.ypos = saved.camera.ypos;
+        camera.zoom = saved.camera.zoom;
+        camera.target_zoom = saved.camera.target_zoom;
+
+        let mut system = StarSystem::from_parts(bodies, life, saved.seed, saved.mass, saved.radius, saved.origin, camera);
+        system.xpos = entry.xpos;
+        system.ypos = entry.ypos;
+        galaxy.entries[saved.index].system = Some(system);
+    }
+
+    galaxy.active = save.active;
+    galaxy.destination = save.destination.filter(|d| *d < galaxy.entries.len());
+    galaxy.sim_time = save.sim_time;
+    galaxy.reset_clocks();
+    Ok(galaxy)
+}
+
+fn restore_body(body: &SavedBody) -> Box<dyn PhysObj> {
+    match *body {
+        SavedBody::Star { x, y, xvel, yvel, mass, radius, seed, temperature } => {
+            Box::new(Star::with_temperature(x, y, xvel, yvel, mass, radius, seed, temperature))
+        },
+        SavedBody::Rocky { x, y, xvel, yvel, mass, radius, seed } => {
+            Box::new(RockyBody::new(x, y, xvel, yvel, mass, radius, seed))
+        },
+        SavedBody::Ship { x, y, xvel, yvel, mass, radius, desired_x, desired_y, max_dv, seed } => {
+            Box::new(Ship::new(x, y, desired_x, desired_y, xvel, yvel, mass, radius, max_dv, seed))
+        },
+        // the player's mass and radius never change, so those
+        // are just what `Player::new` gives
+        SavedBody::Player { x, y, xvel, yvel, .. } => {
+            let mut player = Player::new();
+            player.set_position(x, y);
+            player.set_velocity(xvel, yvel);
+            Box::new(player)
+        },
+    }
+}
+
+// What the save browser shows for each file
+pub struct SaveInfo {
+    pub path: PathBuf,
+    pub label: String,
+    pub saved_at: u64,
+    pub seed: u64,
+    pub system_name: String,
+    pub sim_time: f64,
+}
+
+impl SaveInfo {
+    pub fn describe(&self) -> String {
+        format!(
+            "{:<11} {}  {:<10} {:>9}  seed {}",
+            self.label,
+            format_timestamp(self.saved_at),
+            self.system_name,
+            format_playtime(self.sim_time),
+            self.seed,
+        )
+    }
+}
+
+// The quicksave and every autosave that exists and reads ok,
+// newest first
+pub fn list_saves() -> Vec<SaveInfo> {
+    let mut paths = vec![(String::from("Quicksave"), quicksave_path())];
+    for slot in 0..AUTOSAVE_SLOTS {
+        paths.push((format!("Autosave {}", slot + 1), autosave_path(save_dir(), slot)));
+    }
+    let mut saves = Vec::new();
+    for (label, path) in paths {
+        if !path.exists() {
+            continue;
+        }
+        match read_save(&path) {
+            Ok(save) => saves.push(SaveInfo {
+                path,
+                label,
+                saved_at: save.saved_at,
+                seed: save.seed,
+                system_name: save.system_name,
+                sim_time: save.sim_time,
+            }),
+            Err(e) => println!("WARNING: skipping {}: {}", path.display(), e),
+        }
+    }
+    saves.sort_by_key(|save| Reverse(save.saved_at));
+    saves
+}
+
+// "2026-10-19 14:03" (UTC, nothing here knows about time zones)
+pub fn format_timestamp(unix: u64) -> String {
+    if unix == 0 {
+        return String::from("????-??-?? ??:??");
+    }
+    // days since 1970 to a calendar date (Howard Hinnant's
+    // civil_from_days)
+    let days = (unix / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    let secs = unix % 86400;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year, month, day, secs / 3600, secs % 3600 / 60)
+}
+
+pub fn format_playtime(seconds: f64) -> String {
+    let seconds = seconds.max(0.) as u64;
+    let (h, m, s) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if h > 0 {
+        format!("{}h {:02}m", h, m)
+    } else {
+        format!("{}m {:02}s", m, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CURRENT: &str = "# PARA-GENESIS save
+version = 3
+saved_at = 1760882580
+seed = 42
+system_name = Kazen-7
+sim_time = 125.5
+active = 7
+destination = 12
+
+system = 7
+system_seed = 99
+mass = 123456789
+radius = 25000
+origin = 100 -200
+camera = 10 20 0.5 0.25 lock
+civ = 1 2 3 4 5 6 77 -
+body = star 0 0 0.5 -0.5 1000000 300 11 5800
+body = rocky 5000 0 0 12 5000 40 12
+body = ship 100 200 1 2 10 5 300 400 0.5 13
+body = player 10 20 0 0 1 10
+";
+
+    // the same system as it'd have been written before ships had
+    // seeds, with `header` where v2 added `saved_at`/`system_name`
+    fn old_save(version: u32, header: &str) -> String {
+        format!("# PARA-GENESIS save
+version = {}
+{}seed = 42
+sim_time = 60
+active = 3
+destination = -
+
+system = 3
+system_seed = 5
+mass = 1000
+radius = 20000
+origin = 0 0
+camera = 0 0 1 1 follow
+civ = 1 1 1 1 0 1 9 -
+body = rocky 5000 0 0 12 5000 40 12
+body = ship 100 200 1 2 10 5 300 400 0.5
+body = ship 300 400 1 2 10 5 100 200 0.5
+", version, header)
+    }
+
+    fn ship_seeds(save: &SaveFile) -> Vec<u64> {
+        save.systems[0].bodies.iter()
+            .filter_map(|body| match body {
+                SavedBody::Ship { seed, .. } => Some(*seed),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn current_save_reads_back() {
+        let save = read_save_text(CURRENT).unwrap();
+        assert_eq!(save.version, SAVE_VERSION);
+        assert_eq!(save.saved_at, 1760882580);
+        assert_eq!(save.seed, 42);
+        assert_eq!(save.system_name, "Kazen-7");
+        assert_eq!(save.sim_time, 125.5);
+        assert_eq!(save.active, 7);
+        assert_eq!(save.destination, Some(12));
+        assert_eq!(save.systems.len(), 1);
+
+        let system = &save.systems[0];
+        assert_eq!((system.index, system.seed, system.mass, system.radius), (7, 99, 123456789, 25000.));
+        assert_eq!(system.origin, (100., -200.));
+
+        let camera = &system.camera;
+        assert_eq!((camera.xpos, camera.ypos, camera.zoom, camera.target_zoom), (10., 20., 0.5, 0.25));
+        assert_eq!(camera.mode, CameraMode::LockBody);
+
+        let civ = &system.civ;
+        assert_eq!(
+            (civ.energy_output, civ.dexterity, civ.strength, civ.constitution, civ.damage, civ.size),
+            (1., 2., 3., 4., 5., 6.),
+        );
+        assert_eq!((civ.seed, civ.home_seed), (77, None));
+
+        assert_eq!(system.bodies.len(), 4);
+        assert!(matches!(
+            system.bodies[0],
+            SavedBody::Star { x: 0., y: 0., xvel: 0.5, yvel: -0.5, mass: 1000000, radius: 300., seed: 11, temperature: 5800. },
+        ));
+        assert!(matches!(
+            system.bodies[1],
+            SavedBody::Rocky { x: 5000., y: 0., xvel: 0., yvel: 12., mass: 5000, radius: 40., seed: 12 },
+        ));
+        assert!(matches!(
+            system.bodies[2],
+            SavedBody::Ship {
+                x: 100., y: 200., xvel: 1., yvel: 2., mass: 10, radius: 5.,
+                desired_x: 300., desired_y: 400., max_dv: 0.5, seed: 13,
+            },
+        ));
+        assert!(matches!(
+            system.bodies[3],
+            SavedBody::Player { x: 10., y: 20., xvel: 0., yvel: 0., mass: 1, radius: 10. },
+        ));
+    }
+
+    #[test]
+    fn old_saves_migrate() {
+        let v1 = read_save_text(&old_save(1, "")).unwrap();
+        let v2 = read_save_text(&old_save(2, "saved_at = 1700000000\nsystem_name = Old-1\n")).unwrap();
+        for save in [&v1, &v2] {
+            assert_eq!(save.version, SAVE_VERSION);
+            assert_eq!(save.systems[0].bodies.len(), 3);
+        }
+
+        // v1 never had a name, it comes out of the layout
+        assert_eq!(v1.saved_at, 0);
+        assert_eq!(v1.system_name, layout_galaxy(42)[3].name);
+        assert_eq!(v2.saved_at, 1700000000);
+        assert_eq!(v2.system_name, "Old-1");
+
+        // the ships are lines 15 and 16 once v1's two new lines are
+        // in, so both versions end up with the same seeds
+        let expected = vec![sub_seed(42, 15), sub_seed(42, 16)];
+        assert_eq!(ship_seeds(&v1), expected);
+        assert_eq!(ship_seeds(&v2), expected);
+    }
+
+    #[test]
+    fn bad_saves_are_rejected() {
+        let newer = CURRENT.replace("version = 3", &format!("version = {}", SAVE_VERSION + 1));
+        assert!(read_save_text(&newer).err().unwrap().contains("newer"));
+
+        let elsewhere = CURRENT.replace("active = 7", "active = 8");
+        assert!(read_save_text(&elsewhere).err().unwrap().contains("system the player was in"));
+
+        assert!(read_save_text("seed = 42\n").is_err());
+    }
+
+    // a fresh empty directory under the system temp dir
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("para-genesis-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path, modified: SystemTime) {
+        fs::write(path, "x").unwrap();
+        fs::File::options().write(true).open(path).unwrap().set_modified(modified).unwrap();
+    }
+
+    #[test]
+    fn autosaves_fill_empty_slots_then_reuse_the_oldest() {
+        let dir = scratch_dir("slots");
+        let start = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let at = |secs: u64| start + std::time::Duration::from_secs(secs);
+
+        assert_eq!(next_autosave_slot(&dir), 0);
+        touch(&autosave_path(&dir, 0), at(0));
+        assert_eq!(next_autosave_slot(&dir), 1);
+        // a gap gets filled before anything's overwritten
+        touch(&autosave_path(&dir, 2), at(10));
+        assert_eq!(next_autosave_slot(&dir), 1);
+        touch(&autosave_path(&dir, 1), at(20));
+
+        assert_eq!(next_autosave_slot(&dir), 0);
+        touch(&autosave_path(&dir, 0), at(30));
+        assert_eq!(next_autosave_slot(&dir), 2);
+        touch(&autosave_path(&dir, 2), at(40));
+        assert_eq!(next_autosave_slot(&dir), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = scratch_dir("write");
+        let path = dir.join("save.txt");
+        write_atomic(&path, "old").unwrap();
+        write_atomic(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!path.with_extension("tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // the temp file points at /dev/full, so writing it runs out of
+    // space partway the way a full disk would
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn failed_write_keeps_the_old_save() {
+        let dir = scratch_dir("full");
+        let path = dir.join("save.txt");
+        write_atomic(&path, "old").unwrap();
+        std::os::unix::fs::symlink("/dev/full", path.with_extension("tmp")).unwrap();
+
+        assert!(write_atomic(&path, "new").is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old");
+        assert!(fs::symlink_metadata(path.with_extension("tmp")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(format_timestamp(0), "????-??-?? ??:??");
+        assert_eq!(format_timestamp(1), "1970-01-01 00:00");
+        assert_eq!(format_timestamp(86399), "1970-01-01 23:59");
+        assert_eq!(format_timestamp(951782400), "2000-02-29 00:00");
+        assert_eq!(format_timestamp(1709211845), "2024-02-29 13:04");
+        // 2100 isn't a leap year
+        assert_eq!(format_timestamp(4107542400), "2100-03-01 00:00");
+        assert_eq!(format_timestamp(1760882580), "2025-10-19 14:03");
+    }
+}
@@ -0,0 +1,91 @@
+use macroquad::prelude::*;
+
+use super::menu::*;
+use super::save::*;
+use super::ui::*;
+use super::widgets::*;
+
+pub const SAVES_WINDOW: &str = "saves";
+
+// The "Load game" window: every save on disk, newest first. Same
+// window from the main menu and the pause menu, whoever owns it
+// handles `SelectSave` / `LoadGame` by calling into here.
+pub struct SaveBrowser {
+    pub saves: Vec<SaveInfo>,
+    pub selected: Option<usize>,
+}
+
+impl Default for SaveBrowser {
+    fn default() -> SaveBrowser {
+        SaveBrowser::new()
+    }
+}
+
+impl SaveBrowser {
+    pub fn new() -> SaveBrowser {
+        SaveBrowser {
+            saves: Vec::new(),
+            selected: None,
+        }
+    }
+
+    pub fn window() -> VintageWindow {
+        let mut window = VintageWindow::new(SAVES_WINDOW, "Load game", Vec::new(), None);
+        window.set_position(vec2(120., 120.));
+        window
+    }
+
+    // Rereads the saves folder (an autosave might have landed
+    // since last time) and pops the window up
+    pub fn show(&mut self, ui: &mut UiStack) {
+        self.saves = list_saves();
+        self.selected = if self.saves.is_empty() { None } else { Some(0) };
+        let content = self.content();
+        if let Some(window) = ui.get_mut(SAVES_WINDOW).and_then(|e| e.as_any_mut().downcast_mut::<VintageWindow>()) {
+            window.set_content(content);
+        }
+        ui.show(SAVES_WINDOW);
+    }
+
+    pub fn select(&mut self, i: usize) {
+        if i < self.saves.len() {
+            self.selected = Some(i);
+        }
+    }
+
+    // Reads whichever save is picked, ready to be restored
+    pub fn load_selected(&self) -> Result<SaveFile, String> {
+        let info = self.selected
+            .and_then(|i| self.saves.get(i))
+            .ok_or_else(|| String::from("no save picked"))?;
+        read_save(&info.path)
+    }
+
+    fn content(&self) -> Vec<Box<dyn Widget>> {
+        if self.saves.is_empty() {
+            return vec![
+                Box::new(Label::new("No saves yet.")),
+                Box::new(Centered { child: Box::new(Button::new("OK", UiAction::CloseWindow(SAVES_WINDOW))) }),
+            ];
+        }
+        let mut list = ListBox::new(
+            self.saves.iter().map(|save| save.describe()).collect(),
+            (AUTOSAVE_SLOTS + 1).min(self.saves.len().max(1)),
+            Some(UiAction::SelectSave),
+        );
+        list.width = 640.;
+        list.selected = self.selected;
+        vec![
+            Box::new(Label::new("Pick a save to load:")),
+            Box::new(list),
+            Box::new(Centered {
+                child: Box::new(Row {
+                    children: vec![
+                        Box::new(Button::new("Load", UiAction::LoadGame)),
+                        Box::new(Button::new("Cancel", UiAction::CloseWindow(SAVES_WINDOW))),
+                    ],
+                }),
+            }),
+        ]
+    }
+}
@@ -0,0 +1,98 @@
+use std::fs;
+use std::collections::HashMap;
+
+use super::camera::*;
+
+// plain `key = value` lines, next to wherever the game is run from
+const SETTINGS_PATH: &str = "settings.txt";
+
+// Stuff the player picked that should still be picked next time
+// they launch the game. Anything missing or unreadable in the
+// file just falls back to the default.
+pub struct Settings {
+    pub camera_mode: CameraMode,
+    // top left corner of each UI window, by window id
+    pub window_positions: HashMap<String, (f32, f32)>,
+    // universe from the last game, for Continue on the main menu
+    pub last_seed: Option<u64>,
+    // 0 turns autosaving off
+    pub autosave_minutes: u32,
+}
+
+// what the settings window offers for `autosave_minutes`
+pub const AUTOSAVE_CHOICES: [u32; 5] = [0, 1, 5, 10, 30];
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            camera_mode: CameraMode::Follow,
+            window_positions: HashMap::new(),
+            last_seed: None,
+            autosave_minutes: 5,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+        let contents = match fs::read_to_string(SETTINGS_PATH) {
+            Ok(contents) => contents,
+            Err(_) => return settings,
+        };
+        let values = parse_settings(&contents);
+        if let Some(mode) = values.get("camera_mode").and_then(|v| CameraMode::from_name(v)) {
+            settings.camera_mode = mode;
+        }
+        settings.last_seed = values.get("last_seed").and_then(|v| v.parse::<u64>().ok());
+        if let Some(minutes) = values.get("autosave_minutes").and_then(|v| v.parse::<u32>().ok()) {
+            settings.autosave_minutes = minutes;
+        }
+        for (key, value) in values.iter() {
+            let id = match key.strip_prefix("window.") {
+                Some(id) => id,
+                None => continue,
+            };
+            if let Some((x, y)) = value.split_once(',') {
+                if let (Ok(x), Ok(y)) = (x.trim().parse::<f32>(), y.trim().parse::<f32>()) {
+                    settings.window_positions.insert(id.to_string(), (x, y));
+                }
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) {
+        let mut contents = format!(
+            "camera_mode = {}\nautosave_minutes = {}\n",
+            self.camera_mode.name(),
+            self.autosave_minutes,
+        );
+        if let Some(seed) = self.last_seed {
+            contents += &format!("last_seed = {}\n", seed);
+        }
+        // sorted so the file doesn't reshuffle itself every save
+        let mut windows: Vec<_> = self.window_positions.iter().collect();
+        windows.sort_by(|a, b| a.0.cmp(b.0));
+        for (id, (x, y)) in windows {
+            contents += &format!("window.{} = {}, {}\n", id, x, y);
+        }
+        if let Err(e) = fs::write(SETTINGS_PATH, contents) {
+            println!("WARNING: couldn't save settings: {}", e);
+        }
+    }
+}
+
+fn parse_settings(contents: &str) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            values.insert(key.trim().to_string(), value.trim().to_string());
+        }
+    }
+    values
+}
@@ -0,0 +1,295 @@
+use macroquad::prelude::*;
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
+
+use super::mathtools::*;
+use super::texturetools::*;
+
+// ship sprites are drawn finer than everything else, they're tiny
+pub const SHIP_TEXELS: f32 = 4.;
+
+// so the player's ship is the same one every game
+pub const PLAYER_SHIP_SEED: u64 = 0x5eed_5415;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShipClass {
+    Player,
+    Scout,
+    Fighter,
+    Freighter,
+}
+
+impl ShipClass {
+    // AI ships don't pick, it comes out of their seed
+    pub fn from_seed(seed: u64) -> ShipClass {
+        let mut rng = StdRng::seed_from_u64(sub_seed(seed, 0));
+        match rng.gen_range(0..10) {
+            0..=2 => ShipClass::Scout,
+            3..=7 => ShipClass::Fighter,
+            _ => ShipClass::Freighter,
+        }
+    }
+}
+
+// The colors a faction paints its ships in
+#[derive(Clone, Copy, Debug)]
+pub struct Livery {
+    pub hull: (f32, f32, f32),
+    pub trim: (f32, f32, f32),
+    pub cockpit: (f32, f32, f32),
+    pub engine: (f32, f32, f32),
+}
+
+pub const PLAYER_LIVERY: Livery = Livery {
+    hull: (0.85, 0.87, 0.9),
+    trim: (0.2, 0.45, 0.95),
+    cockpit: (0.35, 0.8, 1.),
+    engine: (0.4, 0.85, 1.),
+};
+
+pub const FACTIONS: [Livery; 5] = [
+    // rust and brass
+    Livery { hull: (0.55, 0.35, 0.25), trim: (0.85, 0.65, 0.2), cockpit: (1., 0.8, 0.3), engine: (1., 0.55, 0.15) },
+    // navy
+    Livery { hull: (0.3, 0.35, 0.5), trim: (0.9, 0.9, 0.95), cockpit: (0.5, 0.9, 1.), engine: (0.55, 0.7, 1.) },
+    // pirates, mostly black
+    Livery { hull: (0.18, 0.18, 0.2), trim: (0.85, 0.15, 0.15), cockpit: (1., 0.3, 0.25), engine: (1., 0.3, 0.2) },
+    // miners
+    Livery { hull: (0.75, 0.7, 0.35), trim: (0.25, 0.25, 0.25), cockpit: (0.6, 1., 0.6), engine: (0.9, 0.9, 0.5) },
+    // something nobody's met yet
+    Livery { hull: (0.35, 0.55, 0.45), trim: (0.75, 0.35, 0.85), cockpit: (0.9, 0.5, 1.), engine: (0.5, 1., 0.7) },
+];
+
+impl Livery {
+    pub fn for_ship(seed: u64, class: ShipClass) -> Livery {
+        match class {
+            ShipClass::Player => PLAYER_LIVERY,
+            _ => FACTIONS[(sub_seed(seed, 1) % FACTIONS.len() as u64) as usize],
+        }
+    }
+}
+
+// How a class's hulls come out. Everything's in ship radii, nose
+// at -length and tail at +length.
+struct HullStyle {
+    length: f32,
+    // widest the body itself gets
+    width: f32,
+    // (half) width along the body, t goes 0 at the nose to 1 at the tail
+    profile: fn(f32) -> f32,
+    // how many stations down each side
+    stations: (usize, usize),
+    wing_chance: f64,
+    wing_span: (f32, f32),
+    engines: (usize, usize),
+}
+
+fn hull_style(class: ShipClass) -> HullStyle {
+    match class {
+        ShipClass::Player => HullStyle {
+            length: 0.78,
+            width: 0.3,
+            profile: |t| t.powf(0.6),
+            stations: (5, 5),
+            wing_chance: 1.,
+            wing_span: (0.7, 0.8),
+            engines: (2, 2),
+        },
+        ShipClass::Scout => HullStyle {
+            length: 0.8,
+            width: 0.2,
+            profile: |t| (t * std::f32::consts::PI).sin().max(0.35).powf(0.6),
+            stations: (4, 6),
+            wing_chance: 0.5,
+            wing_span: (0.45, 0.6),
+            engines: (1, 1),
+        },
+        ShipClass::Fighter => HullStyle {
+            length: 0.75,
+            width: 0.25,
+            profile: |t| t.powf(0.7),
+            stations: (4, 6),
+            wing_chance: 0.9,
+            wing_span: (0.6, 0.85),
+            engines: (1, 2),
+        },
+        ShipClass::Freighter => HullStyle {
+            length: 0.7,
+            width: 0.5,
+            profile: |t| 0.6 + 0.4 * (t * 5.).min(1.),
+            stations: (3, 5),
+            wing_chance: 0.2,
+            wing_span: (0.6, 0.7),
+            engines: (2, 3),
+        },
+    }
+}
+
+// The right hand side of a hull, nose to tail. Mirrored to get
+// the rest so every ship comes out symmetric.
+struct Hull {
+    side: Vec<Vec2>,
+    // (half) width of the body at each station, without wings
+    stations: Vec<Vec2>,
+    wing: Option<[Vec2; 2]>,
+    engines: usize,
+}
+
+impl Hull {
+    fn new(seed: u64, class: ShipClass) -> Hull {
+        let mut rng = StdRng::seed_from_u64(sub_seed(seed, 2));
+        let style = hull_style(class);
+        let count = rng.gen_range(style.stations.0..=style.stations.1);
+        let mut stations = Vec::new();
+        for i in 0..=count {
+            let t = i as f32 / count as f32;
+            let y = -style.length + t * style.length * 2.;
+            let wobble = if i == 0 { 0. } else { rng.gen_range(0.75..1.1) };
+            // never comes to a point at the back, the engines go there
+            let width = ((style.profile)(t) * wobble).max(if i == count { 0.5 } else { 0. }) * style.width;
+            stations.push(vec2(width, y));
+        }
+
+        let mut side = stations.clone();
+        let mut wing = None;
+        if rng.gen_bool(style.wing_chance) {
+            // off somewhere in the back half, swept back from there
+            let root = rng.gen_range(count / 2..count);
+            let span = rng.gen_range(style.wing_span.0..=style.wing_span.1);
+            let front = stations[root];
+            let sweep = rng.gen_range(0.1..0.35);
+            let chord = rng.gen_range(0.08..0.2);
+            let tip = vec2(span, (front.y + sweep).min(style.length));
+            let tip_back = vec2(span * 0.9, (tip.y + chord).min(style.length + 0.05));
+            side.insert(root + 1, tip);
+            side.insert(root + 2, tip_back);
+            wing = Some([tip, tip_back]);
+        }
+        // squeezed so nothing hangs off the sprite
+        let furthest = side.iter().fold(0f32, |m, p| m.max(p.length()));
+        if furthest > 0.95 {
+            let shrink = 0.95 / furthest;
+            for p in side.iter_mut().chain(stations.iter_mut()) {
+                *p *= shrink;
+            }
+            wing = wing.map(|[a, b]| [a * shrink, b * shrink]);
+        }
+
+        Hull {
+            side,
+            stations,
+            wing,
+            engines: rng.gen_range(style.engines.0..=style.engines.1),
+        }
+    }
+
+    // right side then the left coming back up, a closed outline
+    fn outline(&self, squash: f32) -> Vec<Vec2> {
+        let right = self.side.iter().map(|p| vec2(p.x * squash, p.y));
+        let left = self.side.iter().rev().map(|p| vec2(-p.x * squash, p.y));
+        right.chain(left).collect()
+    }
+
+    // body half width at `y`, wings left out
+    fn width_at(&self, y: f32) -> f32 {
+        for pair in self.stations.windows(2) {
+            if y >= pair[0].y && y <= pair[1].y {
+                let t = (y - pair[0].y) / (pair[1].y - pair[0].y).max(1e-6);
+                return pair[0].x + (pair[1].x - pair[0].x) * t;
+            }
+        }
+        0.
+    }
+
+    fn tail(&self) -> Vec2 {
+        *self.stations.last().unwrap()
+    }
+}
+
+fn rgb((r, g, b): (f32, f32, f32), brightness: f32, a: f32) -> Color {
+    Color::new((r * brightness).min(1.), (g * brightness).min(1.), (b * brightness).min(1.), a)
+}
+
+// A ship seen from above, pointing up the screen. The hull, where
+// its wings and engines go and the faction colors all come from the
+// seed, so one seed always gives the same ship; the class picks how
+// it's built and the player always gets their own paint job.
+pub fn create_ship_image(radius: f32, seed: u64, class: ShipClass) -> Image {
+    let size = ((radius * 2. * SHIP_TEXELS).ceil() as u16).max(8) + 2;
+    let mut image = Image::gen_image_color(size, size, Color::new(0., 0., 0., 0.));
+    let mut canvas = Canvas::new(&mut image);
+    let hull = Hull::new(seed, class);
+    let livery = Livery::for_ship(seed, class);
+    let mut rng = StdRng::seed_from_u64(sub_seed(seed, 3));
+
+    // ship radii to texels
+    let center = size as f32 / 2.;
+    let scale = radius * SHIP_TEXELS;
+    let to_image = |p: Vec2| vec2(center + p.x * scale, center + p.y * scale);
+    let polygon = |points: Vec<Vec2>| Shape::Polygon(points.into_iter().map(to_image).collect());
+
+    // engine glow goes under the hull so the nozzles cover half of it
+    let tail = hull.tail();
+    let nozzle = (tail.x * 2. / hull.engines as f32 * 0.35).clamp(0.04, 0.12);
+    let engine_xs: Vec<f32> = match hull.engines {
+        1 => vec![0.],
+        2 => vec![-tail.x * 0.5, tail.x * 0.5],
+        _ => vec![-tail.x * 0.65, 0., tail.x * 0.65],
+    };
+    for &x in engine_xs.iter() {
+        let glow = to_image(vec2(x, tail.y + nozzle * 0.6));
+        let radius = nozzle * 1.5 * scale;
+        canvas.fill(
+            &Shape::Circle { center: glow, radius },
+            Paint::Radial { center: glow, radius, inner: rgb(livery.engine, 1.4, 1.), outer: rgb(livery.engine, 1., 0.) },
+        );
+    }
+
+    // hull, darker at the edges with a lighter spine down the middle
+    let outline = polygon(hull.outline(1.));
+    canvas.fill(&outline, rgb(livery.hull, 0.7, 1.));
+    canvas.fill(&polygon(hull.outline(0.55)), rgb(livery.hull, 1., 1.));
+    canvas.fill(&polygon(hull.outline(0.2)), rgb(livery.hull, 1.2, 1.));
+
+    // faction colors on the wingtips and a band across the body
+    if let Some([tip, tip_back]) = hull.wing {
+        for flip in [1., -1.] {
+            let inner = vec2(tip.x * 0.7, (tip.y + tip_back.y) * 0.5);
+            let points = vec![tip, tip_back, inner].into_iter().map(|p| vec2(p.x * flip, p.y)).collect();
+            canvas.fill(&polygon(points), rgb(livery.trim, 1., 1.));
+        }
+    }
+    let band = rng.gen_range(0.1..0.45) * tail.y;
+    let thickness = rng.gen_range(0.04..0.08);
+    let (top, bottom) = (hull.width_at(band) * 0.9, hull.width_at(band + thickness) * 0.9);
+    let stripe = vec![vec2(-top, band), vec2(top, band), vec2(bottom, band + thickness), vec2(-bottom, band + thickness)];
+    canvas.fill(&polygon(stripe), rgb(livery.trim, 1., 1.));
+
+    // engine housings across the back
+    for &x in engine_xs.iter() {
+        let housing = vec![
+            vec2(x - nozzle, tail.y - nozzle * 1.5),
+            vec2(x + nozzle, tail.y - nozzle * 1.5),
+            vec2(x + nozzle * 0.8, tail.y + nozzle * 0.6),
+            vec2(x - nozzle * 0.8, tail.y + nozzle * 0.6),
+        ];
+        canvas.fill(&polygon(housing), Color::new(0.2, 0.2, 0.22, 1.));
+    }
+    // a darker line round the edge so it stands out against the sky
+    canvas.stroke(&outline, SHIP_TEXELS * 0.4, rgb(livery.hull, 0.4, 1.));
+
+    // cockpit, a rounded bit of glass a little back from the nose
+    let nose = hull.stations[0].y;
+    let front = nose + rng.gen_range(0.2..0.35) * -nose;
+    let back = front + rng.gen_range(0.12..0.25);
+    let glass = (hull.width_at((front + back) * 0.5) * 0.5).clamp(0.04, 0.12);
+    let (glass_front, glass_back) = (to_image(vec2(0., front)), to_image(vec2(0., back)));
+    canvas.fill(
+        &Shape::Line { from: glass_front, to: glass_back, width: glass * 2. * scale },
+        Paint::Linear { from: glass_front, to: glass_back, start: rgb(livery.cockpit, 0.9, 1.), end: rgb(livery.cockpit, 0.45, 1.) },
+    );
+    let shine = to_image(vec2(-glass * 0.35, front));
+    canvas.fill(&Shape::Circle { center: shine, radius: glass * 0.45 * scale }, rgb(livery.cockpit, 1.3, 0.8));
+
+    image
+}
@@ -1,8 +1,8 @@
 use std::time::Instant;
 use macroquad::prelude::*;
-use macroquad::texture::*;
 use super::physics::*;
-use super::camera::*;
+use super::render::*;
+use super::shipgen::*;
 use std::any::Any;
 
 pub struct PlayerShip {
@@ -13,10 +13,8 @@ pub struct PlayerShip {
     pub mass: u64,
     pub radius: f32,
     pub force_vectors: Vec<ForceVector>,
-    //frames: Vec<Texture2D>,
-    //frame_idx: usize,
+    pub id: BodyId,
     pub last_update: Instant,
-    pub texture: Texture2D,
 }
 
 impl PhysObj for PlayerShip {
@@ -29,6 +27,11 @@ impl PhysObj for PlayerShip {
         self
     }
 
+    fn id(&self) -> BodyId { self.id }
+    fn appearance(&self) -> Option<Appearance> {
+        Some(Appearance::Ship { radius: self.radius, seed: PLAYER_SHIP_SEED, class: ShipClass::Player })
+    }
+
     fn xpos(&self) -> f32 { self.xpos }
     fn ypos(&self) -> f32 { self.ypos }
     fn xvel(&self) -> f32 { self.xvel }
@@ -59,12 +62,21 @@ impl PhysObj for PlayerShip {
         self.force_vectors.clone()
     }
 
+    fn reset_clock(&mut self) {
+        self.last_update = Instant::now();
+    }
+
     fn update(
         &mut self,
     ) {
         let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update);
-        
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.step(dt);
+    }
+
+    // integrates whatever forces have piled up over `dt` seconds
+    fn step(&mut self, dt: f32) {
         let mut final_vector: ForceVector = (0., 0.);
         //assume this self as a force_vectors full of vectors
         for i in 0..self.force_vectors.len() {
@@ -77,55 +89,11 @@ impl PhysObj for PlayerShip {
         let ay = final_vector.1 / self.mass as f32;
         
         // v = at
-        self.xvel += ax * elapsed.as_secs_f32(); 
-        self.yvel += ay * elapsed.as_secs_f32();
+        self.xvel += ax * dt; 
+        self.yvel += ay * dt;
 
-        self.xpos += self.xvel * elapsed.as_secs_f32();
-        self.ypos += self.yvel * elapsed.as_secs_f32();
-        
-        self.last_update = now;
-    }
-    fn draw(
-        &mut self,
-        camera: &ZCamera, 
-    ) {
-        //let (tex_x, tex_y) = (self.frames[self.frame_idx].width(), self.frames[self.frame_idx].height());
-        let draw_x = (self.xpos as f64 - camera.xpos as f64) * camera.zoom - 150. * camera.zoom;
-        let draw_y = (self.ypos as f64 - camera.ypos as f64) * camera.zoom - 150. * camera.zoom;
-        
-        draw_texture_ex(
-            &self.texture,
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2(300. * camera.zoom as f32, 300. as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        );
-        /*
-        draw_hexagon(
-            draw_x as f32,
-            draw_y as f32,
-            self.radius * camera.zoom as f32,
-            0.,
-            true,
-            WHITE,
-            GRAY,
-        )
-        //draw_texture(&self.frames[self.frame_idx], draw_x as f32 - (tex_x as f32 * scl_x as f32 / 2.), draw_y as f32 - (tex_y as f32 * scl_y as f32 / 2.), WHITE);
-        draw_texture_ex(
-            &self.frames[self.frame_idx],
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                //dest_size: Some(vec2(screen_width() * camera.zoom as f32, screen_height() * camera.zoom as f32)),
-                dest_size: Some(vec2(WIDTH as f32 * camera.zoom as f32, HEIGHT as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        )
-        */
+        self.xpos += self.xvel * dt;
+        self.ypos += self.yvel * dt;
     }
 }
 
@@ -138,7 +106,6 @@ impl PlayerShip {
         mass: u64,
         radius: f32,
     ) -> PlayerShip {
-        let passthru_rad = radius as f32;
         PlayerShip {
             xpos,
             ypos,
@@ -147,8 +114,8 @@ impl PlayerShip {
             mass,
             radius,
             force_vectors: Vec::new(),
+            id: next_body_id(),
             last_update: Instant::now(),
-            texture: create_ship_texture(passthru_rad),
         }
     }
     pub fn clone(&self) -> PlayerShip {
@@ -160,8 +127,8 @@ impl PlayerShip {
             mass: self.mass,
             radius: self.radius,
             force_vectors: self.force_vectors.clone(),
+            id: self.id,
             last_update: self.last_update,
-            texture: self.texture.clone(),
         }
     }
 }
@@ -175,11 +142,11 @@ pub struct Ship {
     pub mass: u64,
     pub radius: f32,
     pub max_dv: f32,
+    // what it looks like: class, faction and hull all come out of this
+    pub seed: u64,
     pub force_vectors: Vec<ForceVector>,
-    //frames: Vec<Texture2D>,
-    //frame_idx: usize,
+    pub id: BodyId,
     pub last_update: Instant,
-    pub texture: Texture2D,
 }
 
 impl Ship {
@@ -193,8 +160,8 @@ impl Ship {
         mass: u64,
         radius: f32,
         max_dv: f32,
+        seed: u64,
     ) -> Ship {
-        let passthru_rad = radius as f32;
         Ship {
             xpos,
             ypos,
@@ -205,17 +172,17 @@ impl Ship {
             mass,
             radius,
             max_dv,
+            seed,
             force_vectors: Vec::new(),
+            id: next_body_id(),
             last_update: Instant::now(),
-            texture: create_ship_texture(passthru_rad),
         }
     }
 
     fn update_ai(
-        &mut self
+        &mut self,
+        dt: f32,
     ) {
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update);
 
         // The idea now is to impart force vectors to guide the 
         // ship towards the desired point without hitting anything...
@@ -246,8 +213,8 @@ impl Ship {
         
         // v = at
         // Velocity vector (very important)
-        self.xvel += ax * elapsed.as_secs_f32(); 
-        self.yvel += ay * elapsed.as_secs_f32();
+        self.xvel += ax * dt; 
+        self.yvel += ay * dt;
         
         // this is the amount that we IDEALLY want to 
         // change the velocity vector
@@ -262,8 +229,8 @@ impl Ship {
         /*
         self.force_vectors.push(
             (
-                adx / elapsed.as_secs_f32() * self.mass as f32,
-                ady / elapsed.as_secs_f32() * self.mass as f32
+                adx / dt * self.mass as f32,
+                ady / dt * self.mass as f32
             )
         );
         */
@@ -282,6 +249,11 @@ impl PhysObj for Ship {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+
+    fn id(&self) -> BodyId { self.id }
+    fn appearance(&self) -> Option<Appearance> {
+        Some(Appearance::Ship { radius: self.radius, seed: self.seed, class: ShipClass::from_seed(self.seed) })
+    }
     fn xpos(&self) -> f32 { self.xpos }
     fn ypos(&self) -> f32 { self.ypos }
     fn xvel(&self) -> f32 { self.xvel }
@@ -314,15 +286,24 @@ impl PhysObj for Ship {
 
 
 
+    fn reset_clock(&mut self) {
+        self.last_update = Instant::now();
+    }
+
     fn update(
         &mut self,
     ) {
 
-        self.update_ai();
         let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update);
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.step(dt);
+    }
 
-        
+    // steers, then integrates whatever forces have piled up
+    // over `dt` seconds
+    fn step(&mut self, dt: f32) {
+        self.update_ai(dt);
         let mut final_vector: ForceVector = (0., 0.);
         //assume this self as a force_vectors full of vectors
         for i in 0..self.force_vectors.len() {
@@ -335,88 +316,10 @@ impl PhysObj for Ship {
         let ay = final_vector.1 / self.mass as f32;
         
         // v = at
-        self.xvel += ax * elapsed.as_secs_f32(); 
-        self.yvel += ay * elapsed.as_secs_f32();
-
-        self.xpos += self.xvel * elapsed.as_secs_f32();
-        self.ypos += self.yvel * elapsed.as_secs_f32();
-        
-        self.last_update = now;
-    }
-    fn draw(
-        &mut self,
-        camera: &ZCamera, 
-    ) {
-        //let (tex_x, tex_y) = (self.frames[self.frame_idx].width(), self.frames[self.frame_idx].height());
+        self.xvel += ax * dt; 
+        self.yvel += ay * dt;
 
-        let draw_x = (self.xpos as f64 - camera.xpos as f64) * camera.zoom - 150. * camera.zoom;
-        let draw_y = (self.ypos as f64 - camera.ypos as f64) * camera.zoom - 150. * camera.zoom;
-        
-        draw_texture_ex(
-            &self.texture,
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                dest_size: Some(vec2(300. * camera.zoom as f32, 300. as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        );
-        /*
-        draw_hexagon(
-            draw_x as f32,
-            draw_y as f32,
-            self.radius * camera.zoom as f32,
-            0.,
-            true,
-            WHITE,
-            GRAY,
-        )
-        //draw_texture(&self.frames[self.frame_idx], draw_x as f32 - (tex_x as f32 * scl_x as f32 / 2.), draw_y as f32 - (tex_y as f32 * scl_y as f32 / 2.), WHITE);
-        draw_texture_ex(
-            &self.frames[self.frame_idx],
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                //dest_size: Some(vec2(screen_width() * camera.zoom as f32, screen_height() * camera.zoom as f32)),
-                dest_size: Some(vec2(WIDTH as f32 * camera.zoom as f32, HEIGHT as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        )
-        */
+        self.xpos += self.xvel * dt;
+        self.ypos += self.yvel * dt;
     }
 }
-
-pub fn create_ship_texture(radius: f32) -> Texture2D {
-    // Step 1: create a macroquad::texture::Image
-    // Step 2: turn that in to a Texture2D
-    // step 3: return that shit and profit
-    //
-    
-
-    let clear_color = Color {
-        r: 0.,
-        g: 0.,
-        b: 0.,
-        a: 0.,
-    };
-
-    let (width, height) = (300, 300);
-
-    let (cx, cy) = (width as u16 / 2, height as u16 / 2);
-
-    let mut base_img_texture = Image::gen_image_color(width, height, clear_color);
-    
-    for w in 0..width {
-        for h in 0..height {
-            let (dx, dy) = (cx - w, cy - h);
-            let d = ((dx * dx) as f32 + (dy * dy) as f32).sqrt();
-            if d <= radius {
-                base_img_texture.set_pixel(w as u32, h as u32, WHITE);
-            }
-        }
-    };
-
-    Texture2D::from_image(&base_img_texture)
-}
@@ -1,13 +1,16 @@
 use macroquad::prelude::*;
-use std::time::{SystemTime, Instant, Duration};
+use std::time::Instant;
 use std::any::Any;
-use ::rand::Rng;
+use ::rand::{Rng, SeedableRng};
+use ::rand::rngs::StdRng;
 use noise::{NoiseFn, Perlin};
 use ::rand::distributions::{Distribution, Uniform};
 
 use super::physics::*;
-use super::camera::*;
+use super::loading::*;
 use super::mathtools::*;
+use super::texturetools::*;
+use super::render::*;
 
 const WIDTH: u32 = 1000;
 const HEIGHT: u32 = 1000;
@@ -19,11 +22,11 @@ pub struct Star {
     yvel: f32,
     mass: u64,
     radius: f32,
+    seed: u64,
+    temperature: f32,
     force_vectors: Vec<ForceVector>,
-    frames: Vec<Texture2D>,
-    frame_idx: usize,
+    id: BodyId,
     last_update: Instant,
-    last_frame_update: Instant,
 }
 
 impl PhysObj for Star {
@@ -35,6 +38,11 @@ impl PhysObj for Star {
         self
     }
 
+    fn id(&self) -> BodyId { self.id }
+    fn appearance(&self) -> Option<Appearance> {
+        Some(Appearance::Star { radius: self.radius, temperature: self.temperature, seed: self.seed })
+    }
+
     fn xpos(&self) -> f32 { self.xpos }
     fn ypos(&self) -> f32 { self.ypos }
     fn xvel(&self) -> f32 { self.xvel }
@@ -57,21 +65,19 @@ impl PhysObj for Star {
     fn update_ypos(&mut self, update_val: f32) {
         self.ypos += update_val;
     }
+    fn reset_clock(&mut self) {
+        self.last_update = Instant::now();
+    }
+
     fn update(&mut self) {
         let now = Instant::now();
-        let elapsed_frame = now.duration_since(self.last_frame_update);
-        // update the frame to display 7x per second
-        if elapsed_frame >= Duration::from_secs_f32(1. / 7.) {
-            self.last_frame_update = now;
-            if self.frame_idx < self.frames.len() - 1  {
-                self.frame_idx += 1;
-            } else {
-                self.frame_idx = 0;
-            }
-        }
-
-        let elapsed = now.duration_since(self.last_update);
+        let dt = now.duration_since(self.last_update).as_secs_f32();
+        self.last_update = now;
+        self.step(dt);
+    }
 
+    // integrates whatever forces have piled up over `dt` seconds
+    fn step(&mut self, dt: f32) {
         let mut final_vector: ForceVector = (0., 0.);
         //assume this self as a force_vectors full of vectors
         for i in 0..self.force_vectors.len() {
@@ -82,15 +88,13 @@ impl PhysObj for Star {
 
         let ax = final_vector.0 / self.mass as f32;
         let ay = final_vector.1 / self.mass as f32;
-        
+
         // v = at
-        self.xvel += ax * elapsed.as_secs_f32(); 
-        self.yvel += ay * elapsed.as_secs_f32();
+        self.xvel += ax * dt;
+        self.yvel += ay * dt;
 
-        self.xpos += self.xvel * elapsed.as_secs_f32();
-        self.ypos += self.yvel * elapsed.as_secs_f32();
-        
-        self.last_update = now;
+        self.xpos += self.xvel * dt;
+        self.ypos += self.yvel * dt;
 
     }
     fn add_vector(&mut self, force_vec: ForceVector) {
@@ -100,140 +104,150 @@ impl PhysObj for Star {
         self.force_vectors.clone()
     }
 
-    fn draw(
-        &mut self, 
-        camera: &ZCamera,
-    ) {
-        
-        /*
-        * I want to check and see if the star is within the bounds
-        * of the camera, and only draw it if it is. 
-        */
-        let draw_x = (self.xpos as f64 - (WIDTH as f64 / 2.) - camera.xpos as f64) * camera.zoom;
-        let draw_y = (self.ypos as f64 - (WIDTH as f64 / 2.) - camera.ypos as f64) * camera.zoom;
-        //draw_texture(&self.frames[self.frame_idx], draw_x as f32 - (tex_x as f32 * scl_x as f32 / 2.), draw_y as f32 - (tex_y as f32 * scl_y as f32 / 2.), WHITE);
-        draw_texture_ex(
-            &self.frames[self.frame_idx],
-            draw_x as f32,
-            draw_y as f32,
-            WHITE,
-            DrawTextureParams {
-                //dest_size: Some(vec2(screen_width() * camera.zoom as f32, screen_height() * camera.zoom as f32)),
-                dest_size: Some(vec2(WIDTH as f32 * camera.zoom as f32, HEIGHT as f32 * camera.zoom as f32)),
-                ..Default::default()
-            }
-        )
-    }
     //fn set_xvel(&mut self, xvel: f32) { self.xvel = xvel }
     //fn set_yvel(&mut self, yvel: f32) { self.yvel = yvel }
 }
 
 impl Star {
 
-    pub  async fn new(
+    pub fn new(
         xpos: f32,
         ypos: f32,
         xvel: f32,
         yvel: f32,
         mass: u64,
         radius: f32,
+        seed: u64,
         //ctx: &mut Context 
     ) -> Star {
-        let frames = gen_rand_star_textures(radius).await;
+        Star::with_temperature(xpos, ypos, xvel, yvel, mass, radius, seed, star_temperature(seed))
+    }
+
+    // Same as `new` but with the temperature given rather than
+    // rolled from the seed (loading a save)
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_temperature(
+        xpos: f32,
+        ypos: f32,
+        xvel: f32,
+        yvel: f32,
+        mass: u64,
+        radius: f32,
+        seed: u64,
+        temperature: f32,
+    ) -> Star {
         Star {
             xpos: xpos,
             ypos: ypos,
             xvel,
             yvel,
-            frames,
             mass,
             radius,
+            seed,
+            temperature,
             force_vectors: Vec::new(),
-            frame_idx: 0,
+            id: next_body_id(),
             last_update: Instant::now(),
-            last_frame_update: Instant::now(),
         }
     }
+
+    // Merges keep the bigger star's id, so as far as drawing goes it's
+    // the same star and keeps its old look until the new one's made
+    pub fn with_id(mut self, id: BodyId) -> Star {
+        self.id = id;
+        self
+    }
+
+    pub fn seed(&self) -> u64 { self.seed }
+
+    pub fn temperature(&self) -> f32 { self.temperature }
 }
 
-pub async fn gen_rand_star_textures(radius: f32) -> Vec<Texture2D> {
-    let n_frames = 10;
-    let mut textures: Vec<Texture2D> = Vec::new();
-    let star_temp = ::rand::thread_rng().gen_range(4000..11000);
+pub fn star_temperature(seed: u64) -> f32 {
+    StdRng::seed_from_u64(seed).gen_range(4000..11000) as f32
+}
 
-    for _ in 0..n_frames {
-        textures.push(create_star_texture(radius, star_temp as f32));
-    }
-    textures
+// How many frames one loop of a star's surface takes. They play at
+// `STAR_FPS`, so this is a two second loop.
+pub const STAR_FRAMES: usize = 16;
+pub const STAR_FPS: f64 = 8.;
+
+// how far along the noise's time axis one loop goes. more = the
+// surface churns faster
+const STAR_LOOP_LENGTH: f64 = 1.2;
+
+// limb darkening coefficient, 0 = flat disc, 1 = black at the edge.
+// the sun's is about 0.6
+const LIMB_DARKENING: f32 = 0.6;
+
+pub fn gen_star_images(radius: f32, star_temp: f32, seed: u64) -> Vec<Image> {
+    (0..STAR_FRAMES)
+        .map(|frame| create_star_image(radius, star_temp, seed, frame as f64 / STAR_FRAMES as f64))
+        .collect()
 }
 
-pub fn create_star_texture(r: f32, temp: f32) -> Texture2D {
-    let now = SystemTime::now();
-    let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH)
-        .expect("Time went backwards");
-    let seed = (
-        since_epoch.as_secs() * 1_000_000_000 + since_epoch
-            .subsec_nanos() as u64 
-    ) as u32;
-    let clear_color = Color{
-        r: 0.,
-        g: 0.,
-        b: 0.,
-        a: 0.,
-    };
-
-    let (width, height) = (WIDTH as u32, HEIGHT as u32);
-
-    let (cx, cy) = (width as u16 / 2, height as u16 / 2);
-
-    let mut base_img_texture = Image::gen_image_color(width as u16, height as u16, clear_color);
-
-    let perlin = Perlin::new(seed);
-    let base_color = temp_to_color(temp);
-    let (red, green, blue) = base_color;
+// roughly the average of what the noise does to the base color
+pub fn star_lod_color(temperature: f32) -> Color {
+    let (red, green, blue) = temp_to_color(temperature);
+    Color::new(red * 0.7, green * 0.7, (blue * 1.15).min(1.), 1.)
+}
+
+// One frame of a star, `phase` of the way (0..1) through its loop.
+// Every frame comes from the same noise, just further along in time,
+// so granulation and sunspots drift instead of flickering, and the
+// last frame runs straight back into the first.
+pub fn create_star_image(r: f32, temp: f32, seed: u64, phase: f64) -> Image {
+    let (cx, cy) = (WIDTH as i32 / 2, HEIGHT as i32 / 2);
+
+    let granulation = Perlin::new(perlin_seed(seed));
+    let spots = Perlin::new(perlin_seed(sub_seed(seed, 1)));
+    let streamers = Perlin::new(perlin_seed(sub_seed(seed, 2)));
+    let (red, green, blue) = temp_to_color(temp);
     let p = r + r * 0.3;
     let v = p - r;
 
-    for w in 0..width {
-        for h in 0..height {
-
-            let (dx, dy) = (cx as u32 - w, cy as u32 - h);
-            let d = ((dx * dx) as f32 + (dy * dy) as f32).sqrt();
-
-            //if this pixel is inside the radius of the circle...
-            if d <= r {
-                let noise_val = perlin.get([w as f64 / 2., h as f64 / 2.]);
-
-
-                let mod_red = (red as f64 * (noise_val + 1.4) / 2.) as f32;
-                let mod_green = (green as f64 * (noise_val + 1.4) / 2.) as f32;
-                let mod_blue = (blue as f64 * (noise_val + 2.3) / 2.) as f32;
-                base_img_texture.set_pixel(w as u32, h as u32, Color{r: mod_red, g: mod_green, b: mod_blue, a: 1.});
-
-            // if this pixel is outside the radius of the circle, but inside the radius p
-            // (p defined above. p~r )
-            } else if d <= p {
-                let q = d - r;
-                let noise_val = perlin.get([w as f64 / 2., h as f64 / 2.]);
-                let alpha = (0.75 * (1. - q / v)) as f32;
-                let mod_red = (red as f64 * (noise_val + 1.4) / 2.) as f32;
-                let mod_green = (green as f64 * (noise_val + 1.4) / 2.) as f32;
-                let mod_blue = (blue as f64 * (noise_val + 2.3) / 2.) as f32;
-                base_img_texture.set_pixel(
-                    w as u32,
-                    h as u32,
-                    Color { 
-                        r: mod_red,
-                        g: mod_green,
-                        b: mod_blue,
-                        a: alpha
-                    }
-                );
-            }
+    par_image(WIDTH as u16, HEIGHT as u16, |w, h| {
+        let (dx, dy) = (cx - w as i32, cy - h as i32);
+        let d = ((dx * dx) as f32 + (dy * dy) as f32).sqrt();
+
+        //if this pixel is inside the radius of the circle...
+        if d <= r {
+            // small bubbling cells all over
+            let noise_val = looping_noise(&granulation, w as f64 / 6., h as f64 / 6., phase, STAR_LOOP_LENGTH);
+            // big slow blotches, the darkest bits of which are sunspots
+            let spot_val = looping_noise(&spots, w as f64 / 80., h as f64 / 80., phase, STAR_LOOP_LENGTH * 0.25);
+            let spot = 1. - 0.6 * ((spot_val as f32 - 0.35) / 0.25).clamp(0., 1.);
+            // dimmer towards the edge, we're looking through more of
+            // the star's atmosphere there
+            let mu = (1. - (d / r) * (d / r)).max(0.).sqrt();
+            let limb = (1. - LIMB_DARKENING * (1. - mu)) * spot;
+
+            let mod_red = red * ((noise_val + 1.4) / 2.) as f32 * limb;
+            let mod_green = green * ((noise_val + 1.4) / 2.) as f32 * limb;
+            let mod_blue = blue * ((noise_val + 2.3) / 2.) as f32 * limb;
+            Color { r: mod_red, g: mod_green, b: mod_blue, a: 1. }.into()
+
+        // if this pixel is outside the radius of the circle, but inside the radius p
+        // (p defined above. p~r ) it's corona, a glow that fades out towards p
+        } else if d <= p {
+            let q = (d - r) / v;
+            // sampled round a circle so the streamers point outwards
+            // and there's no seam where the angle wraps
+            let angle = (dy as f64).atan2(dx as f64);
+            let streamer = looping_noise(&streamers, angle.cos() * 3., angle.sin() * 3., phase, STAR_LOOP_LENGTH * 0.5);
+            let streamer = ((streamer + 1.) / 2.) as f32;
+            let alpha = 0.75 * (1. - q) * (1. - q) * (0.5 + 0.5 * streamer);
+            // hotter than the surface, so a bit whiter too
+            Color {
+                r: red + (1. - red) * 0.35,
+                g: green + (1. - green) * 0.35,
+                b: blue + (1. - blue) * 0.35,
+                a: alpha,
+            }.into()
+        } else {
+            CLEAR_PIXEL
         }
-    };
-
-    Texture2D::from_image(&base_img_texture)
+    })
 }
 
 // Some really messed up stuff
@@ -273,11 +287,11 @@ fn fast_root(n :f32) -> f32 {
     1. / fast_inverse_sqrt(n)
 }
 
-async fn initialize_rand_star(win_width: f32, win_height: f32, x1: f32, y1: f32) -> Star {
+fn initialize_rand_star(win_width: f32, win_height: f32, x1: f32, y1: f32, seed: u64) -> Star {
     let win_width = win_width as i32;
     let win_height = win_height as i32;
     let vel_distribution = Uniform::new(0.0f32, 2.0f32);
-    let mut rng = ::rand::thread_rng();
+    let mut rng = StdRng::seed_from_u64(seed);
     // TODO CHANGE THIS - THREAT LEVEL: MIDNIGHT 
     let mass = rng.gen_range(10000..1000000000000);
     let r = r_from_mass(mass as f32, (10000., 1000000000000.), (5., 20.)) / 2.;
@@ -297,16 +311,23 @@ async fn initialize_rand_star(win_width: f32, win_height: f32, x1: f32, y1: f32)
         (vel_distribution.sample(&mut rng) - 1.) * 15.,
         mass,
         r,
-    ).await
+        sub_seed(seed, 1),
+    )
 }
 
 
+// the big central star plus however many extra wander about
+pub const DESIRED_STARS: usize = 0;
+
 pub async fn load_stars(
     stars: &mut Vec<Box<dyn PhysObj>>,
     win_width: f32,
-    win_height: f32
+    win_height: f32,
+    seed: u64,
+    loading: &mut Loading,
 ) -> (f32, f32, u64) {
-    let desired_stars = 0;
+    let desired_stars = DESIRED_STARS as u64;
+    loading.stage("Igniting stars");
     let (big_x, big_y) = (win_width / 2., win_height / 2.);
     let mass: u64 = 599999999999999999;
     stars.push(
@@ -317,19 +338,21 @@ pub async fn load_stars(
                 0.,
                 0.,
                 mass,
-                335.)
-            .await
+                335.,
+                sub_seed(seed, 0))
         )
     );
+    loading.tick().await;
 
-    for _ in 0..desired_stars {
-        stars.push(Box::new(initialize_rand_star(win_width, win_height, big_x, big_y).await));
+    for i in 0..desired_stars {
+        stars.push(Box::new(initialize_rand_star(win_width, win_height, big_x, big_y, sub_seed(seed, i + 1))));
+        loading.tick().await;
     }
     (big_x, big_y, mass)
 }
 
-async fn initialize_particle(win_width: i32, win_height: i32) -> Star {
-    let mut rng = ::rand::thread_rng();
+fn initialize_particle(win_width: i32, win_height: i32, seed: u64) -> Star {
+    let mut rng = StdRng::seed_from_u64(seed);
     Star::new(
         rng.gen_range(0..win_width) as f32,
         rng.gen_range(0..win_height) as f32,
@@ -337,5 +360,25 @@ async fn initialize_particle(win_width: i32, win_height: i32) -> Star {
         0.,
         1000000,
         1.,
-    ).await
+        sub_seed(seed, 1),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_same_star() {
+        let a = create_star_image(40., 5800., 42, 0.25);
+        let b = create_star_image(40., 5800., 42, 0.25);
+        assert!(a.bytes == b.bytes);
+    }
+
+    #[test]
+    fn different_seed_different_star() {
+        let a = create_star_image(40., 5800., 42, 0.25);
+        let b = create_star_image(40., 5800., 43, 0.25);
+        assert!(a.bytes != b.bytes);
+    }
 }
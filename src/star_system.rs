@@ -13,10 +13,18 @@ use super::player::*;
 use super::mathtools::*;
 use super::life::*;
 use super::ships::*;
+use super::loading::*;
+use super::render::*;
 
 const WIDTH: u32 = 1000;
 const HEIGHT: u32 = 1000;
 
+// Systems are laid out as if on a screen this big. (Used to be
+// the actual window, which meant the same seed gave a different
+// system at every window size, and couldn't run without one.)
+pub const LAYOUT_WIDTH: f32 = 1920.;
+pub const LAYOUT_HEIGHT: f32 = 1080.;
+
 
 pub struct StarSystem {
     pub bodies: Vec<Box<dyn PhysObj>>,
@@ -29,41 +37,81 @@ pub struct StarSystem {
     pub radius: f32,
     pub camera: ZCamera,
     pub force_vectors: Vec<ForceVector>,
+    // where the central star was spawned, in system coords.
+    // `radius` is measured out from here.
+    pub origin: (f32, f32),
+    pub seed: u64,
+    pub render_stats: RenderStats,
+    pub grid: SpatialGrid,
 }
 
 impl StarSystem {
-    pub async fn new_rand(player: Player) -> StarSystem {
+    // Everything in the system (placement, masses, textures,
+    // the civilization) comes out of `seed`, so the same seed
+    // always gives back the exact same system.
+    //
+    // The player isn't part of a freshly made system, it gets
+    // dropped in with `place_player` / `sync_player`.
+    pub async fn new_rand(seed: u64, loading: &mut Loading) -> StarSystem {
         let mut loaded = false;
         let mut fs = false;
 
        
         let mut bodies: Vec<Box< dyn PhysObj>> = Vec::new();
-        bodies.push(Box::new(player));
-        let mut life: Civilization = load_civilization();
+        let mut life: Civilization = load_civilization(sub_seed(seed, 0));
         let cam = ZCamera::new_origin();
+        loading.expect(1 + DESIRED_STARS + NUM_ROCKY_BODIES);
 
         let (ox, oy, m) = load_stars(
             &mut bodies,
-            screen_width() as f32,
-            screen_height() as f32
+            LAYOUT_WIDTH,
+            LAYOUT_HEIGHT,
+            sub_seed(seed, 1),
+            loading,
         ).await;
 
         load_rocky_bodies(
             &mut loaded,
             &mut bodies,
-            screen_width() as f32,
-            screen_height() as f32,
+            LAYOUT_WIDTH,
+            LAYOUT_HEIGHT,
             ox,
             oy,
-            m
+            m,
+            sub_seed(seed, 2),
+            loading,
         ).await;
 
+        // the civilization lives on one of the rocky bodies. Tracked by
+        // the body's seed since indices shuffle around on collisions
+        let rocky_seeds: Vec<u64> = bodies.iter()
+            .filter_map(|body| body.as_any().downcast_ref::<RockyBody>())
+            .map(|body| body.seed())
+            .collect();
+        if !rocky_seeds.is_empty() {
+            let home = (sub_seed(seed, 3) % rocky_seeds.len() as u64) as usize;
+            life.home_seed = Some(rocky_seeds[home]);
+        }
+
+        // the edge of the system is a bit past the farthest body.
+        // flying past it is how the player leaves for another system
+        let mut radius: f32 = 0.;
+        for body in bodies.iter() {
+            let (dx, dy) = (body.xpos() - ox, body.ypos() - oy);
+            radius = radius.max((dx * dx + dy * dy).sqrt());
+        }
+        radius *= 1.2;
+
         StarSystem {
             bodies,
             life,
-            mass: 0,
+            mass: m,
             force_vectors: Vec::new(),
-            radius: 0.,
+            radius,
+            origin: (ox, oy),
+            seed,
+            render_stats: RenderStats::default(),
+            grid: SpatialGrid::new(1000.),
             xpos: 0.,
             ypos: 0.,
             xvel: 0.,
@@ -72,20 +120,125 @@ impl StarSystem {
         }
     }
 
-    // Once there are multiple 'versions' of the player
-    // when there are multiple star systems,
-    // we should set the overall games player to equal
-    // the most recently updated player in all the star systems
-    // then, we make all those other star system players equal
-    // to the game's player
-    //
-    // Okay im from over a year in the future and past me
-    // was really on one when making this code base
+    // A system put back together from a save. Nothing gets rolled,
+    // the bodies already carry everything they need.
+    pub fn from_parts(
+        bodies: Vec<Box<dyn PhysObj>>,
+        life: Civilization,
+        seed: u64,
+        mass: u64,
+        radius: f32,
+        origin: (f32, f32),
+        camera: ZCamera,
+    ) -> StarSystem {
+        StarSystem {
+            bodies,
+            life,
+            mass,
+            force_vectors: Vec::new(),
+            radius,
+            origin,
+            seed,
+            render_stats: RenderStats::default(),
+            grid: SpatialGrid::new(1000.),
+            xpos: 0.,
+            ypos: 0.,
+            xvel: 0.,
+            yvel: 0.,
+            camera,
+        }
+    }
+
+    // One fixed `dt` step of the whole system: gravity, collisions,
+    // then moving everything. No clocks and no camera, so it runs
+    // the same with or without a window.
+    pub fn simulate(&mut self, dt: f32) {
+        update_gravity_physics(&mut self.bodies);
+        check_collisions(&mut self.bodies);
+        step_bodies(&mut self.bodies, dt);
+    }
+
+    // `time` is seconds of simulation (see `Galaxy::sim_time`), so
+    // anything animated holds still while the game's paused
+    pub fn draw(&mut self, render: &mut RenderCache, time: f64) {
+        render.update();
+        // by id rather than by count, a merge and a new body in the
+        // same frame leave the count where it was
+        render.forget_missing(&self.bodies);
+        let mut stats = RenderStats::default();
+        for body in self.bodies.iter() {
+            // stars and planets have a glow/atmosphere out past their radius
+            if !self.camera.is_visible(body.xpos(), body.ypos(), body.radius() * 1.3) {
+                stats.culled += 1;
+                continue;
+            }
+            if self.camera.use_lod(body.radius()) {
+                stats.lod += 1;
+            }
+            stats.drawn += 1;
+            render.draw_body(body.as_ref(), &self.camera, time);
+        }
+        self.render_stats = stats;
+    }
+
+    // The player only ever lives in one system at a time, and
+    // always sits at the front of that system's `bodies`
+    // (the camera's follow mode relies on that).
+    pub fn player(&self) -> Option<&Player> {
+        self.bodies.first()?.as_any().downcast_ref::<Player>()
+    }
+
+    pub fn take_player(&mut self) -> Option<Player> {
+        let player = self.player()?.clone();
+        self.bodies.remove(0);
+        Some(player)
+    }
+
+    pub fn place_player(&mut self, mut player: Player, x: f32, y: f32) {
+        player.set_position(x, y);
+        self.bodies.insert(0, Box::new(player));
+    }
+
+    // This is the handoff between systems: the player is pulled
+    // out of `from` and dropped into `to` at (x, y), keeping
+    // whatever velocity it left with.
     //
-    // I think in the modern day i would just hide 
-    // everything behind traits and RefCell Boxes & stuff.
-    pub fn sync_player(player: &mut Player) {
-         
+    // (past me wanted to keep a copy of the player in every
+    // system and sync them all up. Just moving the one player
+    // around is a lot less cursed.)
+    pub fn sync_player(from: &mut StarSystem, to: &mut StarSystem, x: f32, y: f32) {
+        if let Some(player) = from.take_player() {
+            to.place_player(player, x, y);
+        }
+    }
+
+    // The body at world (x, y), or the closest one whose edge is
+    // within `slop` of it. Goes through `grid`, so it's only as up
+    // to date as the last `update`.
+    pub fn body_at(&self, x: f32, y: f32, slop: f32) -> Option<usize> {
+        let mut best: Option<(usize, f32)> = None;
+        for i in self.grid.query(x, y, slop) {
+            let body = match self.bodies.get(i) {
+                Some(body) => body,
+                None => continue,
+            };
+            let (dx, dy) = (body.xpos() - x, body.ypos() - y);
+            let gap = (dx * dx + dy * dy).sqrt() - body.radius();
+            if gap <= slop && best.is_none_or(|(_, best_gap)| gap < best_gap) {
+                best = Some((i, gap));
+            }
+        }
+        best.map(|(i, _)| i)
+    }
+
+    pub fn player_outside_edge(&self) -> bool {
+        match self.player() {
+            Some(player) => {
+                let (dx, dy) = (player.xpos() - self.origin.0, player.ypos() - self.origin.1);
+                (dx * dx + dy * dy).sqrt() > self.radius
+            },
+            None => false,
+        }
     }
 }
 
@@ -96,6 +249,8 @@ impl PhysObj for StarSystem {
     fn as_any_mut(&mut self) -> &mut dyn Any {
         self
     }
+    fn id(&self) -> BodyId { 0 }
+    fn appearance(&self) -> Option<Appearance> { None }
     fn xpos(&self) -> f32 { self.xpos }
     fn ypos(&self) -> f32 { self.ypos }
     fn xvel(&self) -> f32 { self.xvel }
@@ -115,19 +270,25 @@ impl PhysObj for StarSystem {
     fn force_vectors(&self) -> Vec<ForceVector> {
         vec![(0., 0.)]
     }
-    fn update(&mut self){
+    fn reset_clock(&mut self) {
         for body in self.bodies.iter_mut() {
-            body.update();
-        }
-        if let Some(ship) = self.bodies.first_mut() {
-            follow_ship(ship, &mut self.camera);
+            body.reset_clock();
         }
-             
     }
-    fn draw(&mut self, dummy_cam: &ZCamera) {
-        for body in self.bodies.iter_mut() {
-            body.draw(&self.camera);
+    fn step(&mut self, dt: f32) {
+        step_bodies(&mut self.bodies, dt);
+    }
+    fn update(&mut self){
+        // the player goes first on its own, it reads the keyboard
+        let players = if self.player().is_some() { 1 } else { 0 };
+        let (player, rest) = self.bodies.split_at_mut(players);
+        for body in player.iter_mut() {
+            body.update();
         }
+        update_bodies(rest);
+        self.camera.update(&self.bodies, get_frame_time());
+        self.grid.rebuild(&self.bodies);
+             
     }
 }
  
@@ -0,0 +1,196 @@
+use macroquad::prelude::*;
+
+use super::ui::*;
+
+pub const TASKBAR_HEIGHT: f32 = 28.;
+const START_WIDTH: f32 = 64.;
+const BUTTON_MAX_WIDTH: f32 = 160.;
+const MENU_ITEM_HEIGHT: f32 = 26.;
+const MENU_WIDTH: f32 = 180.;
+// the blue strip down the left of the start menu
+const MENU_BAND: f32 = 22.;
+
+// What a click on the taskbar turned out to be for.
+pub enum TaskbarClick {
+    Nothing,
+    Action(UiAction),
+    Window(&'static str),
+}
+
+// The strip along the bottom of the screen. One button per open
+// window (minimized or not) and a Start menu full of actions.
+pub struct Taskbar {
+    pub start_open: bool,
+    start_items: Vec<(String, UiAction)>,
+    // windows in the order they were opened, so buttons don't
+    // shuffle around every time something comes to the front
+    order: Vec<&'static str>,
+}
+
+impl Taskbar {
+    pub fn new(start_items: Vec<(String, UiAction)>) -> Taskbar {
+        Taskbar {
+            start_open: false,
+            start_items,
+            order: Vec::new(),
+        }
+    }
+
+    pub fn rect() -> Rect {
+        Rect::new(0., screen_height() - TASKBAR_HEIGHT, screen_width(), TASKBAR_HEIGHT)
+    }
+
+    fn start_button() -> Rect {
+        let bar = Taskbar::rect();
+        Rect::new(bar.x + 2., bar.y + 3., START_WIDTH, bar.h - 5.)
+    }
+
+    fn menu_rect(&self) -> Rect {
+        let h = self.start_items.len() as f32 * MENU_ITEM_HEIGHT + 6.;
+        Rect::new(2., Taskbar::rect().y - h, MENU_WIDTH, h)
+    }
+
+    fn menu_item(&self, i: usize) -> Rect {
+        let menu = self.menu_rect();
+        Rect::new(menu.x + MENU_BAND + 3., menu.y + 3. + i as f32 * MENU_ITEM_HEIGHT, menu.w - MENU_BAND - 6., MENU_ITEM_HEIGHT)
+    }
+
+    fn window_buttons(&self) -> Vec<Rect> {
+        let bar = Taskbar::rect();
+        let start = Taskbar::start_button();
+        let x0 = start.x + start.w + 6.;
+        let n = self.order.len().max(1) as f32;
+        let w = ((bar.w - x0 - 4.) / n).min(BUTTON_MAX_WIDTH);
+        (0..self.order.len())
+            .map(|i| Rect::new(x0 + i as f32 * w, start.y, w - 3., start.h))
+            .collect()
+    }
+
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        let point = vec2(x, y);
+        Taskbar::rect().contains(point) || (self.start_open && self.menu_rect().contains(point))
+    }
+
+    // keep the button list in step with which windows are open
+    pub fn sync(&mut self, elements: &[Box<dyn UiElement>]) {
+        self.order.retain(|id| elements.iter().any(|e| e.id() == *id && e.is_open()));
+        for e in elements.iter() {
+            if e.is_open() && !self.order.contains(&e.id()) {
+                self.order.push(e.id());
+            }
+        }
+    }
+
+    pub fn click(&mut self, input: &UiInput) -> TaskbarClick {
+        if !input.pressed {
+            return TaskbarClick::Nothing;
+        }
+        if Taskbar::start_button().contains(input.mouse) {
+            self.start_open = !self.start_open;
+            return TaskbarClick::Nothing;
+        }
+        if self.start_open {
+            let picked = (0..self.start_items.len()).find(|i| self.menu_item(*i).contains(input.mouse));
+            self.start_open = false;
+            if let Some(i) = picked {
+                return TaskbarClick::Action(self.start_items[i].1.clone());
+            }
+        }
+        for (id, rect) in self.order.iter().zip(self.window_buttons()) {
+            if rect.contains(input.mouse) {
+                return TaskbarClick::Window(id);
+            }
+        }
+        TaskbarClick::Nothing
+    }
+
+    pub fn draw(&self, elements: &[Box<dyn UiElement>], focused: Option<&'static str>) {
+        let bar = Taskbar::rect();
+        draw_rectangle(bar.x, bar.y, bar.w, bar.h, WIN98_FACE);
+        draw_line(bar.x, bar.y + 1., bar.x + bar.w, bar.y + 1., 2., WIN98_HIGHLIGHT);
+
+        let start = Taskbar::start_button();
+        draw_rectangle(start.x, start.y, start.w, start.h, WIN98_FACE);
+        draw_bevel(start, !self.start_open);
+        draw_start_logo(start.x + 6., start.y + 5., start.h - 10.);
+        draw_ui_text("Start", start.x + start.h + 2., start.y + 3., FONT_SIZE, WIN98_TEXT);
+
+        for (id, rect) in self.order.iter().zip(self.window_buttons()) {
+            let e = match elements.iter().find(|e| e.id() == *id) {
+                Some(e) => e,
+                None => continue,
+            };
+            // the focused, un-minimized window's button looks pushed in
+            let active = focused == Some(*id) && !e.is_minimized();
+            draw_rectangle(rect.x, rect.y, rect.w, rect.h, if active { WIN98_HIGHLIGHT } else { WIN98_FACE });
+            draw_bevel(rect, !active);
+            draw_ui_text(&fit_text(e.title(), rect.w - 10.), rect.x + 5., rect.y + 3., FONT_SIZE, WIN98_TEXT);
+        }
+
+        if self.start_open {
+            let menu = self.menu_rect();
+            draw_rectangle(menu.x, menu.y, menu.w, menu.h, WIN98_FACE);
+            draw_bevel(menu, true);
+            draw_rectangle(menu.x + 3., menu.y + 3., MENU_BAND, menu.h - 6., WIN98_TITLE);
+            draw_text_ex(
+                "PARA-GENESIS 98",
+                menu.x + MENU_BAND - 4.,
+                menu.y + menu.h - 8.,
+                TextParams {
+                    font_size: 16,
+                    color: WHITE,
+                    rotation: -std::f32::consts::FRAC_PI_2,
+                    ..Default::default()
+                }
+            );
+            let (mouse_x, mouse_y) = mouse_position();
+            for (i, (text, _)) in self.start_items.iter().enumerate() {
+                let item = self.menu_item(i);
+                let color = if item.contains(vec2(mouse_x, mouse_y)) {
+                    draw_rectangle(item.x, item.y, item.w, item.h, WIN98_TITLE);
+                    WHITE
+                } else {
+                    WIN98_TEXT
+                };
+                draw_ui_text(text, item.x + 8., item.y + 4., FONT_SIZE, color);
+            }
+        }
+    }
+}
+
+// The four colored panes of the Start logo, slightly waved
+// like the real one
+fn draw_start_logo(x: f32, y: f32, size: f32) {
+    let half = size / 2. - 1.;
+    let panes = [
+        (0., 0., Color::new(0.93, 0.26, 0.14, 1.)),
+        (1., 0., Color::new(0.39, 0.72, 0.18, 1.)),
+        (0., 1., Color::new(0.13, 0.47, 0.87, 1.)),
+        (1., 1., Color::new(0.99, 0.80, 0.12, 1.)),
+    ];
+    for (col, row, color) in panes.iter() {
+        let px = x + col * (half + 2.);
+        let py = y + row * (half + 2.);
+        // one pixel column at a time, nudged up and down by a sine
+        for i in 0..half as i32 {
+            let wave = ((i as f32 + col * half) / size * std::f32::consts::PI).sin() * 1.5;
+            draw_rectangle(px + i as f32, py - wave, 1., half, *color);
+        }
+    }
+}
+
+// chop a title down with "..." until it fits in `width` pixels
+fn fit_text(text: &str, width: f32) -> String {
+    if text_size(text, FONT_SIZE).x <= width {
+        return String::from(text);
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let candidate: String = chars.iter().collect::<String>() + "...";
+        if text_size(&candidate, FONT_SIZE).x <= width {
+            return candidate;
+        }
+    }
+    String::new()
+}
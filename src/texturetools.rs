@@ -1,53 +1,435 @@
-use std::time::Instant;
 use macroquad::prelude::*;
-use macroquad::texture::*;
-use super::physics::*;
-use super::camera::*;
-use super::mathtools::*;
-use std::any::Any;
+use rayon::prelude::*;
+use noise::{NoiseFn, Perlin};
 
 
-pub fn draw_image_line(x1: u32, y1: u32, x2: u32, y2: u32, thickness: u8, image: &mut Image, color: Color) {
+pub const CLEAR_PIXEL: [u8; 4] = [0, 0, 0, 0];
 
-    let (width, height) = (image.width, image.height);
+// Builds a width x height image straight into an RGBA buffer, rows
+// spread over every core. `pixel(x, y)` gets called once per pixel
+// in no particular order, so it can't depend on any other pixel.
+pub fn par_image<F>(width: u16, height: u16, pixel: F) -> Image
+where
+    F: Fn(u32, u32) -> [u8; 4] + Sync,
+{
+    let mut bytes = vec![0u8; width as usize * height as usize * 4];
+    if width > 0 {
+        bytes.par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+            for (x, out) in row.chunks_exact_mut(4).enumerate() {
+                out.copy_from_slice(&pixel(x as u32, y as u32));
+            }
+        });
+    }
+    Image { bytes, width, height }
+}
+
+// `over` painted on top of `under`. Whatever shows through of
+// `under` counts for as much as it's see-through itself, so drawing
+// on a clear image doesn't drag the edges towards black.
+pub fn overlay_pixel(under: [u8; 4], over: [u8; 4]) -> [u8; 4] {
+    let c1: Color = under.into();
+    let c2: Color = over.into();
+    let a = c2.a + c1.a * (1. - c2.a);
+    if a <= 0. {
+        return CLEAR_PIXEL;
+    }
+    let mix = |u: f32, o: f32| ((o * c2.a + u * c1.a * (1. - c2.a)) / a).min(1.);
+    Color {
+        r: mix(c1.r, c2.r),
+        g: mix(c1.g, c2.g),
+        b: mix(c1.b, c2.b),
+        a: a.min(1.),
+    }.into()
+}
+
+// Something to draw on an image. Everything's in pixels with (0, 0)
+// the top left corner of the top left pixel, so pixel (x, y) has its
+// middle at (x + 0.5, y + 0.5). Edges get anti-aliased by how far
+// each pixel's middle is from them, so a rect lined up on whole
+// pixels still comes out with hard edges.
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Rect(Rect),
+    RoundedRect { rect: Rect, radius: f32 },
+    Circle { center: Vec2, radius: f32 },
+    Ellipse { center: Vec2, radii: Vec2 },
+    // `width` across, with round ends
+    Line { from: Vec2, to: Vec2, width: f32 },
+    // in order, either way round, closed back to the first point.
+    // crossed over bits go by even-odd
+    Polygon(Vec<Vec2>),
+}
+
+impl Shape {
+    // How far `p` is outside the edge, negative inside
+    pub fn distance(&self, p: Vec2) -> f32 {
+        match self {
+            Shape::Rect(rect) => rounded_box_distance(p, rect, 0.),
+            Shape::RoundedRect { rect, radius } => rounded_box_distance(p, rect, *radius),
+            Shape::Circle { center, radius } => p.distance(*center) - radius,
+            Shape::Ellipse { center, radii } => {
+                if radii.x <= 0. || radii.y <= 0. {
+                    return f32::INFINITY;
+                }
+                // how far off the ellipse's equation is, over how fast
+                // that changes. only right near the edge, but that's
+                // the only place it matters
+                let d = p - *center;
+                let off = (d / *radii).length_squared() - 1.;
+                let slope = (d / (*radii * *radii)).length() * 2.;
+                if slope < 1e-6 { -radii.min_element() } else { off / slope }
+            },
+            Shape::Line { from, to, width } => segment_distance(p, *from, *to) - width * 0.5,
+            Shape::Polygon(points) => {
+                if points.len() < 3 {
+                    return f32::INFINITY;
+                }
+                let mut nearest = f32::INFINITY;
+                let mut inside = false;
+                for (i, a) in points.iter().enumerate() {
+                    let b = points[(i + 1) % points.len()];
+                    nearest = nearest.min(segment_distance(p, *a, b));
+                    // half open, so a point level with a corner isn't counted twice
+                    if (a.y <= p.y) != (b.y <= p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+                        inside = !inside;
+                    }
+                }
+                if inside { -nearest } else { nearest }
+            },
+        }
+    }
+
+    // everything the shape covers is in here
+    pub fn bounds(&self) -> Rect {
+        match self {
+            Shape::Rect(rect) | Shape::RoundedRect { rect, .. } => *rect,
+            Shape::Circle { center, radius } => Rect::new(center.x - radius, center.y - radius, radius * 2., radius * 2.),
+            Shape::Ellipse { center, radii } => Rect::new(center.x - radii.x, center.y - radii.y, radii.x * 2., radii.y * 2.),
+            Shape::Line { from, to, width } => {
+                let (min, max) = (from.min(*to), from.max(*to));
+                Rect::new(min.x - width * 0.5, min.y - width * 0.5, max.x - min.x + width, max.y - min.y + width)
+            },
+            Shape::Polygon(points) => {
+                let min = points.iter().fold(Vec2::splat(f32::INFINITY), |m, p| m.min(*p));
+                let max = points.iter().fold(Vec2::splat(f32::NEG_INFINITY), |m, p| m.max(*p));
+                Rect::new(min.x, min.y, max.x - min.x, max.y - min.y)
+            },
+        }
+    }
+}
+
+fn segment_distance(p: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let along = b - a;
+    let length = along.length_squared();
+    let t = if length > 0. { ((p - a).dot(along) / length).clamp(0., 1.) } else { 0. };
+    p.distance(a + along * t)
+}
+
+fn rounded_box_distance(p: Vec2, rect: &Rect, radius: f32) -> f32 {
+    let half = vec2(rect.w, rect.h) * 0.5;
+    let radius = radius.clamp(0., half.min_element().max(0.));
+    let q = (p - rect.center()).abs() - half + Vec2::splat(radius);
+    q.max(Vec2::ZERO).length() + q.max_element().min(0.) - radius
+}
 
-    let (a, b, c) = line_equation(x1 as f64, y1 as f64, x2 as f64, y2 as f64);
+// What a shape gets filled in with
+#[derive(Clone, Copy, Debug)]
+pub enum Paint {
+    Solid(Color),
+    // `start` at `from` to `end` at `to`, and flat past either end
+    Linear { from: Vec2, to: Vec2, start: Color, end: Color },
+    // `inner` in the middle out to `outer` at `radius` and beyond
+    Radial { center: Vec2, radius: f32, inner: Color, outer: Color },
+}
+
+impl Paint {
+    pub fn at(&self, p: Vec2) -> Color {
+        match *self {
+            Paint::Solid(color) => color,
+            Paint::Linear { from, to, start, end } => {
+                let along = to - from;
+                let t = if along.length_squared() > 0. { (p - from).dot(along) / along.length_squared() } else { 0. };
+                mix_color(start, end, t)
+            },
+            Paint::Radial { center, radius, inner, outer } => {
+                let t = if radius > 0. { p.distance(center) / radius } else { 1. };
+                mix_color(inner, outer, t)
+            },
+        }
+    }
+}
+
+impl From<Color> for Paint {
+    fn from(color: Color) -> Paint {
+        Paint::Solid(color)
+    }
+}
+
+// `t` of the way from `a` to `b`, clamped to the two
+pub fn mix_color(a: Color, b: Color, t: f32) -> Color {
+    let t = t.clamp(0., 1.);
+    Color::new(
+        a.r + (b.r - a.r) * t,
+        a.g + (b.g - a.g) * t,
+        a.b + (b.b - a.b) * t,
+        a.a + (b.a - a.a) * t,
+    )
+}
 
-    let min_x = std::cmp::min(x1, x2);
-    let max_x = std::cmp::max(x1, x2);
-    let min_y = std::cmp::min(y1, y2);
-    let max_y = std::cmp::max(y1, y2);
-    
-    // vertical line case
-    if x1 == x2 {
-        for w in (x1 - (thickness as u32 / 2))..=(x1 + (thickness as u32 / 2)) {
-            for h in min_y..=max_y {
-                image.set_pixel(w as u32, h as u32, color)
+// Draws shapes onto an image, blending them over what's there.
+// Nothing outside the clip rect (the whole image to start with)
+// gets touched, so shapes can hang off the edges or be cut down to
+// part of the image without every caller checking.
+pub struct Canvas<'a> {
+    pub image: &'a mut Image,
+    // whole pixels, left/top in and right/bottom out
+    clip: (i32, i32, i32, i32),
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(image: &'a mut Image) -> Canvas<'a> {
+        let clip = (0, 0, image.width as i32, image.height as i32);
+        Canvas { image, clip }
+    }
+
+    // Only draws inside `rect` (rounded out to whole pixels, and never
+    // off the image) until `unclip`
+    pub fn clip_to(&mut self, rect: Rect) {
+        self.clip = (
+            (rect.x.floor() as i32).max(0),
+            (rect.y.floor() as i32).max(0),
+            (rect.right().ceil() as i32).min(self.image.width as i32),
+            (rect.bottom().ceil() as i32).min(self.image.height as i32),
+        );
+    }
+
+    pub fn unclip(&mut self) {
+        self.clip = (0, 0, self.image.width as i32, self.image.height as i32);
+    }
+
+    // `color` over whatever's at (x, y), if it's inside the clip
+    pub fn blend(&mut self, x: i32, y: i32, color: Color) {
+        let (left, top, right, bottom) = self.clip;
+        if x < left || y < top || x >= right || y >= bottom || color.a <= 0. {
+            return;
+        }
+        let i = (y as usize * self.image.width as usize + x as usize) * 4;
+        let under = [self.image.bytes[i], self.image.bytes[i + 1], self.image.bytes[i + 2], self.image.bytes[i + 3]];
+        self.image.bytes[i..i + 4].copy_from_slice(&overlay_pixel(under, color.into()));
+    }
+
+    pub fn fill(&mut self, shape: &Shape, paint: impl Into<Paint>) {
+        self.cover(shape.bounds(), paint.into(), |p| shape.distance(p));
+    }
+
+    // just the edge, `width` across and centered on it
+    pub fn stroke(&mut self, shape: &Shape, width: f32, paint: impl Into<Paint>) {
+        let half = width * 0.5;
+        let bounds = shape.bounds();
+        let bounds = Rect::new(bounds.x - half, bounds.y - half, bounds.w + width, bounds.h + width);
+        self.cover(bounds, paint.into(), |p| shape.distance(p).abs() - half);
+    }
+
+    // Every pixel in `bounds` gets `paint` as much as it's covered.
+    // Edges are taken to be straight across a pixel, so one right
+    // on its middle covers half of it.
+    fn cover(&mut self, bounds: Rect, paint: Paint, distance: impl Fn(Vec2) -> f32) {
+        let (left, top, right, bottom) = self.clip;
+        // a pixel out so the soft edges aren't cut off
+        let (x0, x1) = ((bounds.x.floor() as i32 - 1).max(left), (bounds.right().ceil() as i32 + 1).min(right));
+        let (y0, y1) = ((bounds.y.floor() as i32 - 1).max(top), (bounds.bottom().ceil() as i32 + 1).min(bottom));
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let p = vec2(x as f32 + 0.5, y as f32 + 0.5);
+                let coverage = (0.5 - distance(p)).clamp(0., 1.);
+                if coverage > 0. {
+                    let mut color = paint.at(p);
+                    color.a *= coverage;
+                    self.blend(x, y, color);
+                }
             }
         }
     }
-    // horozontal line case
-    if y1 == y2 {
-        for w in min_x..=max_x {
-            for h in (y1 - (thickness as u32 / 2))..=(y1 + (thickness as u32 / 2)) {
-                image.set_pixel(w as u32, h as u32, color)
+}
+
+// 3D noise with time as the third axis, `phase` (0..1) of the way
+// through a loop `length` long. Crossfades from where we are to
+// the same spot one loop back, so phase 1 comes out exactly the
+// same as phase 0 and animations wrap without a jump.
+pub fn looping_noise(noise: &Perlin, x: f64, y: f64, phase: f64, length: f64) -> f64 {
+    let t = phase * length;
+    let now = noise.get([x, y, t]);
+    let back = noise.get([x, y, t - length]);
+    // mixing two noises flattens them out halfway through,
+    // scale back up so spots don't fade out mid loop
+    let spread = ((1. - phase) * (1. - phase) + phase * phase).sqrt();
+    (now * (1. - phase) + back * phase) / spread
+}
+
+// 2D noise that repeats every `width` x `height`, for textures
+// that get tiled. Same crossfade trick as `looping_noise`, on both
+// axes at once.
+pub fn tiling_noise(noise: &Perlin, x: f64, y: f64, width: f64, height: f64) -> f64 {
+    let (s, t) = ((x / width).rem_euclid(1.), (y / height).rem_euclid(1.));
+    let (x, y) = (s * width, t * height);
+    let here = noise.get([x, y]);
+    let left = noise.get([x - width, y]);
+    let up = noise.get([x, y - height]);
+    let both = noise.get([x - width, y - height]);
+    let mixed = (here * (1. - s) + left * s) * (1. - t) + (up * (1. - s) + both * s) * t;
+    let spread = (((1. - s) * (1. - s) + s * s) * ((1. - t) * (1. - t) + t * t)).sqrt();
+    mixed / spread
+}
+
+// average color of everything that isn't see-through. used as the
+// color a body gets drawn with when it's too small for its texture
+pub fn average_color(image: &Image) -> Color {
+    let (mut r, mut g, mut b, mut n) = (0., 0., 0., 0.);
+    for pixel in image.get_image_data().iter() {
+        if pixel[3] > 127 {
+            r += pixel[0] as f32;
+            g += pixel[1] as f32;
+            b += pixel[2] as f32;
+            n += 1.;
+        }
+    }
+    if n == 0. {
+        return WHITE;
+    }
+    Color::new(r / n / 255., g / n / 255., b / n / 255., 1.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clear(width: u16, height: u16) -> Image {
+        Image::gen_image_color(width, height, Color::new(0., 0., 0., 0.))
+    }
+
+    fn pixel(image: &Image, x: u32, y: u32) -> [u8; 4] {
+        let i = (y as usize * image.width as usize + x as usize) * 4;
+        [image.bytes[i], image.bytes[i + 1], image.bytes[i + 2], image.bytes[i + 3]]
+    }
+
+    fn near(value: u8, expected: u8) -> bool {
+        (value as i32 - expected as i32).abs() <= 1
+    }
+
+    #[test]
+    fn pixel_aligned_rect_has_hard_edges() {
+        let mut image = clear(8, 8);
+        Canvas::new(&mut image).fill(&Shape::Rect(Rect::new(2., 2., 3., 3.)), Color::new(1., 0., 0., 1.));
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..5).contains(&x) && (2..5).contains(&y);
+                let expected = if inside { [255, 0, 0, 255] } else { CLEAR_PIXEL };
+                assert_eq!(pixel(&image, x, y), expected, "pixel ({}, {})", x, y);
             }
         }
     }
 
-    for w in min_x..=max_x {
-        for h in min_y..=max_y {
+    #[test]
+    fn edge_through_a_pixel_middle_covers_half() {
+        // circle edge right through the middle of (4, 1)
+        let mut image = clear(9, 9);
+        Canvas::new(&mut image).fill(&Shape::Circle { center: vec2(4.5, 4.5), radius: 3. }, WHITE);
+        assert!(near(pixel(&image, 4, 1)[3], 128));
+        assert_eq!(pixel(&image, 4, 4)[3], 255);
+        assert_eq!(pixel(&image, 0, 0)[3], 0);
 
-            let mut numerator = (a * w as f64 + b * h as f64 + c).abs();
-            let denominator = ((a * a) + (b * b)).sqrt();
+        // and the long edge of a triangle
+        let mut image = clear(10, 10);
+        Canvas::new(&mut image).fill(&Shape::Polygon(vec![vec2(0., 0.), vec2(10., 0.), vec2(0., 10.)]), WHITE);
+        assert!(near(pixel(&image, 4, 5)[3], 128));
+        assert_eq!(pixel(&image, 1, 1)[3], 255);
+        assert_eq!(pixel(&image, 8, 8)[3], 0);
+    }
 
-            let d = numerator / denominator;
+    #[test]
+    fn stroke_through_pixel_middles_is_one_pixel_wide() {
+        let mut image = clear(8, 8);
+        Canvas::new(&mut image).stroke(&Shape::Rect(Rect::new(1.5, 1.5, 4., 4.)), 1., WHITE);
+        for y in 0..8 {
+            for x in 0..8 {
+                let in_box = (1..=5).contains(&x) && (1..=5).contains(&y);
+                let ring = in_box && (x == 1 || x == 5 || y == 1 || y == 5);
+                assert_eq!(pixel(&image, x, y)[3], if ring { 255 } else { 0 }, "pixel ({}, {})", x, y);
+            }
+        }
+    }
 
-            if d <= thickness as f64 + 0.1 {
-                image.set_pixel(w as u32, h as u32, color);
+    #[test]
+    fn clip_to_keeps_drawing_inside() {
+        let mut image = clear(8, 8);
+        let mut canvas = Canvas::new(&mut image);
+        canvas.clip_to(Rect::new(0., 0., 4., 8.));
+        canvas.fill(&Shape::Rect(Rect::new(-10., -10., 30., 30.)), WHITE);
+        canvas.blend(6, 6, WHITE);
+        canvas.unclip();
+        canvas.blend(7, 7, WHITE);
+        for y in 0..8 {
+            for x in 0..8 {
+                let drawn = x < 4 || (x, y) == (7, 7);
+                assert_eq!(pixel(&image, x, y)[3], if drawn { 255 } else { 0 }, "pixel ({}, {})", x, y);
             }
         }
     }
-}
 
+    #[test]
+    fn crossed_polygon_goes_by_even_odd() {
+        // five pointed star drawn in one go, the pentagon in the
+        // middle is inside twice so it stays empty
+        let points: Vec<Vec2> = (0..5)
+            .map(|i| {
+                let angle = i as f32 * 4. * std::f32::consts::PI / 5. - std::f32::consts::FRAC_PI_2;
+                vec2(20. + 18. * angle.cos(), 20. + 18. * angle.sin())
+            })
+            .collect();
+        let mut image = clear(40, 40);
+        Canvas::new(&mut image).fill(&Shape::Polygon(points), WHITE);
+        assert_eq!(pixel(&image, 20, 20)[3], 0);
+        assert_eq!(pixel(&image, 20, 5)[3], 255);
+    }
+
+    #[test]
+    fn gradients_hit_their_end_colors() {
+        let mut image = clear(11, 1);
+        let paint = Paint::Linear { from: vec2(0.5, 0.5), to: vec2(10.5, 0.5), start: BLACK, end: WHITE };
+        Canvas::new(&mut image).fill(&Shape::Rect(Rect::new(0., 0., 11., 1.)), paint);
+        assert_eq!(pixel(&image, 0, 0)[0], 0);
+        assert_eq!(pixel(&image, 10, 0)[0], 255);
+        assert!(near(pixel(&image, 5, 0)[0], 128));
+
+        let mut image = clear(21, 21);
+        let paint = Paint::Radial { center: vec2(10.5, 10.5), radius: 10., inner: WHITE, outer: BLACK };
+        Canvas::new(&mut image).fill(&Shape::Rect(Rect::new(0., 0., 21., 21.)), paint);
+        assert_eq!(pixel(&image, 10, 10)[0], 255);
+        assert_eq!(pixel(&image, 0, 10)[0], 0);
+    }
 
+    #[test]
+    fn overlay_on_clear_keeps_the_color() {
+        let out = overlay_pixel(CLEAR_PIXEL, [255, 0, 0, 128]);
+        assert_eq!(out, [255, 0, 0, 128]);
+        assert_eq!(overlay_pixel(CLEAR_PIXEL, CLEAR_PIXEL), CLEAR_PIXEL);
+        // half red over solid blue is half and half, and solid
+        let out = overlay_pixel([0, 0, 255, 255], [255, 0, 0, 128]);
+        assert!(near(out[0], 128) && near(out[2], 127) && out[3] == 255, "{:?}", out);
+    }
+
+    #[test]
+    fn shapes_off_the_image_dont_panic() {
+        let mut image = clear(8, 8);
+        let mut canvas = Canvas::new(&mut image);
+        canvas.fill(&Shape::Line { from: vec2(0., 0.), to: vec2(0., 7.), width: 3. }, WHITE);
+        canvas.fill(&Shape::Line { from: vec2(-5., -5.), to: vec2(50., 3.), width: 9. }, WHITE);
+        canvas.fill(&Shape::Circle { center: vec2(-100., 4.), radius: 3. }, WHITE);
+        canvas.stroke(&Shape::Ellipse { center: vec2(7., 7.), radii: vec2(20., 2.) }, 2., WHITE);
+        canvas.fill(&Shape::RoundedRect { rect: Rect::new(6., 6., 40., 40.), radius: 5. }, WHITE);
+        canvas.fill(&Shape::Polygon(vec![vec2(-3., -3.), vec2(20., 1.), vec2(4., 30.)]), WHITE);
+        canvas.clip_to(Rect::new(-50., -50., 500., 500.));
+        canvas.fill(&Shape::Rect(Rect::new(0., 0., 8., 8.)), WHITE);
+        assert_eq!(pixel(&image, 7, 7)[3], 255);
+    }
+}
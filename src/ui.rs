@@ -0,0 +1,417 @@
+use std::any::Any;
+use std::collections::HashMap;
+use macroquad::prelude::*;
+
+use super::taskbar::*;
+use super::camera::*;
+
+// The Windows 98 palette. Everything that draws UI takes its
+// colors from these, so it all matches.
+pub const WIN98_FACE: Color = Color { r: 0.83921, g: 0.82745, b: 0.80784, a: 1.0 };
+pub const WIN98_HIGHLIGHT: Color = Color { r: 1.0, g: 0.99215, b: 0.97254, a: 1.0 };
+pub const WIN98_SEMI_SHADOW: Color = Color { r: 0.80392, g: 0.80392, b: 0.77254, a: 1.0 };
+pub const WIN98_SHADOW: Color = Color { r: 0.25490, g: 0.25038, b: 0.266666, a: 1.0 };
+pub const WIN98_TITLE: Color = Color { r: 0.031372, g: 0.141176, b: 0.419607, a: 1.0 };
+pub const WIN98_TEXT: Color = Color { r: 0.0, g: 0.0, b: 0.0, a: 1.0 };
+pub const WIN98_FIELD: Color = Color { r: 1.0, g: 1.0, b: 1.0, a: 1.0 };
+
+pub const FONT_SIZE: u16 = 18;
+// space around and between widgets inside a window
+pub const PADDING: f32 = 10.;
+pub const SPACING: f32 = 6.;
+
+// What the UI wants the game to do. Widgets never reach into the
+// game themselves, they just push one of these and `Game` deals
+// with it once all the UI has had its turn.
+#[derive(Clone, Debug, PartialEq)]
+pub enum UiAction {
+    Quit,
+    // a window was closed from its title bar (by id)
+    CloseWindow(&'static str),
+    // a window got dragged somewhere new (by id)
+    WindowMoved(&'static str),
+    ShowWindow(&'static str),
+    ToggleMap,
+    ToggleDebugOverlay,
+    SetCameraMode(CameraMode),
+    SetAutosaveMinutes(u32),
+    // main menu buttons
+    NewGame,
+    Continue,
+    // pause menu buttons
+    Pause,
+    Resume,
+    SaveGame,
+    QuitToMenu,
+    // save browser: pick a row, then load it
+    SelectSave(usize),
+    LoadGame,
+}
+
+// Everything the UI needs to know about this frame's input, read
+// once up front. (`get_char_pressed` eats the chars it returns, so
+// it can't be called from every text box.)
+#[derive(Clone)]
+pub struct UiInput {
+    pub mouse: Vec2,
+    pub pressed: bool,
+    pub down: bool,
+    pub released: bool,
+    pub wheel: f32,
+    pub chars: Vec<char>,
+    pub keys_pressed: Vec<KeyCode>,
+}
+
+impl UiInput {
+    pub fn gather() -> UiInput {
+        let mut chars = Vec::new();
+        while let Some(c) = get_char_pressed() {
+            chars.push(c);
+        }
+        UiInput {
+            mouse: Vec2::from(mouse_position()),
+            pressed: is_mouse_button_pressed(MouseButton::Left),
+            down: is_mouse_button_down(MouseButton::Left),
+            released: is_mouse_button_released(MouseButton::Left),
+            wheel: mouse_wheel().1,
+            chars,
+            keys_pressed: get_keys_pressed().into_iter().collect(),
+        }
+    }
+
+    pub fn key_pressed(&self, key: KeyCode) -> bool {
+        self.keys_pressed.contains(&key)
+    }
+
+    // What an element that isn't under the mouse gets to see:
+    // the cursor is nowhere and no buttons are down.
+    pub fn without_mouse(&self) -> UiInput {
+        UiInput {
+            mouse: vec2(f32::NEG_INFINITY, f32::NEG_INFINITY),
+            pressed: false,
+            down: false,
+            released: false,
+            wheel: 0.,
+            ..self.clone()
+        }
+    }
+
+    pub fn without_keys(&self) -> UiInput {
+        UiInput {
+            chars: Vec::new(),
+            keys_pressed: Vec::new(),
+            ..self.clone()
+        }
+    }
+}
+
+// Something on screen that takes input: a whole window, a taskbar.
+pub trait UiElement {
+    fn id(&self) -> &'static str;
+    // Handles this frame's input. Returns true if the input was
+    // used up, so nothing under it should react.
+    fn update(&mut self, input: &UiInput, actions: &mut Vec<UiAction>) -> bool;
+    fn draw(&self);
+    fn is_visible(&self) -> bool;
+    fn set_visible(&mut self, visible: bool);
+    // whether screen point (x, y) is on top of this element
+    fn contains(&self, x: f32, y: f32) -> bool;
+    // true while something in here (a text box) is taking keys,
+    // so the game shouldn't treat them as hotkeys
+    fn wants_keyboard(&self) -> bool {
+        false
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    // what the taskbar button says
+    fn title(&self) -> &str {
+        self.id()
+    }
+    // Open windows get a taskbar button. A minimized window is
+    // still open, it just isn't visible.
+    fn is_open(&self) -> bool {
+        self.is_visible()
+    }
+    fn is_minimized(&self) -> bool {
+        false
+    }
+    fn set_minimized(&mut self, _minimized: bool) {}
+    // top left corner, None until the element has been moved
+    fn position(&self) -> Option<Vec2> {
+        None
+    }
+    fn set_position(&mut self, _position: Vec2) {}
+}
+
+// Something that lives inside a window. The window lays widgets
+// out from their `size`, then hands each one its rect.
+pub trait Widget {
+    fn size(&self) -> Vec2;
+    fn update(&mut self, _rect: Rect, _input: &UiInput, _actions: &mut Vec<UiAction>) -> bool {
+        false
+    }
+    fn draw(&self, rect: Rect);
+    fn wants_keyboard(&self) -> bool {
+        false
+    }
+    // what's typed in, for widgets you can type in
+    fn text_value(&self) -> Option<String> {
+        None
+    }
+}
+
+// What came out of routing one frame of input through the UI.
+pub struct UiResponse {
+    // the mouse was over (or dragging) some window
+    pub mouse: bool,
+    // keys went to a window, the game shouldn't see them
+    pub keyboard: bool,
+    pub actions: Vec<UiAction>,
+}
+
+// All the windows, bottom to top, plus the taskbar which always
+// sits over them. Input goes through it from the top down: the
+// mouse belongs to the highest window under the cursor (or
+// whichever one a drag started in), the keyboard to the focused
+// window. Whatever nobody takes is left for the game.
+pub struct UiStack {
+    elements: Vec<Box<dyn UiElement>>,
+    taskbar: Taskbar,
+    focused: Option<&'static str>,
+    // window the left button went down in, it keeps the mouse
+    // until the button comes back up
+    captured: Option<&'static str>,
+}
+
+impl UiStack {
+    pub fn new(start_items: Vec<(String, UiAction)>) -> UiStack {
+        UiStack {
+            elements: Vec::new(),
+            taskbar: Taskbar::new(start_items),
+            focused: None,
+            captured: None,
+        }
+    }
+
+    pub fn push(&mut self, element: Box<dyn UiElement>) {
+        self.elements.push(element);
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut Box<dyn UiElement>> {
+        self.elements.iter_mut().find(|e| e.id() == id)
+    }
+
+    pub fn is_visible(&self, id: &str) -> bool {
+        self.elements.iter().any(|e| e.id() == id && e.is_visible())
+    }
+
+    pub fn is_open(&self, id: &str) -> bool {
+        self.elements.iter().any(|e| e.id() == id && e.is_open())
+    }
+
+    pub fn position(&self, id: &str) -> Option<Vec2> {
+        self.elements.iter().find(|e| e.id() == id).and_then(|e| e.position())
+    }
+
+    // put windows back where they were last session
+    pub fn restore_positions(&mut self, positions: &HashMap<String, (f32, f32)>) {
+        for e in self.elements.iter_mut() {
+            if let Some((x, y)) = positions.get(e.id()) {
+                e.set_position(vec2(*x, *y));
+            }
+        }
+    }
+
+    pub fn bring_to_front(&mut self, id: &str) {
+        if let Some(i) = self.elements.iter().position(|e| e.id() == id) {
+            let element = self.elements.remove(i);
+            self.focused = Some(element.id());
+            self.elements.push(element);
+        }
+    }
+
+    // pops a window up on top with focus
+    pub fn show(&mut self, id: &str) {
+        if let Some(e) = self.get_mut(id) {
+            e.set_visible(true);
+            e.set_minimized(false);
+        }
+        self.bring_to_front(id);
+    }
+
+    // what clicking a window's taskbar button does: restore it if
+    // it's minimized, minimize it if it's already on top, otherwise
+    // just bring it up
+    fn toggle_from_taskbar(&mut self, id: &'static str) {
+        let minimized = self.elements.iter().any(|e| e.id() == id && e.is_minimized());
+        if minimized {
+            self.show(id);
+        } else if self.focused == Some(id) {
+            if let Some(e) = self.get_mut(id) {
+                e.set_minimized(true);
+            }
+            self.fix_focus();
+        } else {
+            self.bring_to_front(id);
+        }
+    }
+
+    pub fn hide(&mut self, id: &str) {
+        if let Some(e) = self.get_mut(id) {
+            e.set_visible(false);
+        }
+        self.fix_focus();
+    }
+
+    // focus falls to the topmost window still showing
+    fn fix_focus(&mut self) {
+        let focused_visible = self.focused.is_some_and(|id| self.is_visible(id));
+        if !focused_visible {
+            self.focused = self.elements.iter().rev().find(|e| e.is_visible()).map(|e| e.id());
+        }
+        if self.captured.is_some_and(|id| !self.is_visible(id)) {
+            self.captured = None;
+        }
+    }
+
+    pub fn route(&mut self, input: &UiInput) -> UiResponse {
+        let mut actions = Vec::new();
+        self.fix_focus();
+
+        // Escape shuts the start menu, or else closes the focused
+        // window, like it always has
+        let mut keyboard = false;
+        let mut input = input.clone();
+        if input.key_pressed(KeyCode::Escape) {
+            if self.taskbar.start_open {
+                self.taskbar.start_open = false;
+                keyboard = true;
+            } else if let Some(id) = self.focused {
+                self.hide(id);
+                actions.push(UiAction::CloseWindow(id));
+                keyboard = true;
+            }
+            if keyboard {
+                input.keys_pressed.retain(|k| *k != KeyCode::Escape);
+            }
+        }
+
+        // the taskbar is over everything, it gets first look
+        self.taskbar.sync(&self.elements);
+        let over_taskbar = self.captured.is_none() && self.taskbar.contains(input.mouse.x, input.mouse.y);
+        if over_taskbar {
+            match self.taskbar.click(&input) {
+                TaskbarClick::Action(action) => actions.push(action),
+                TaskbarClick::Window(id) => self.toggle_from_taskbar(id),
+                TaskbarClick::Nothing => {},
+            }
+        } else if input.pressed {
+            // clicking anywhere else closes the start menu
+            self.taskbar.start_open = false;
+        }
+
+        let mouse_owner = match self.captured {
+            _ if over_taskbar => None,
+            Some(id) => Some(id),
+            None => self.elements.iter().rev()
+                .find(|e| e.is_visible() && e.contains(input.mouse.x, input.mouse.y))
+                .map(|e| e.id()),
+        };
+        if input.pressed {
+            if let Some(id) = mouse_owner {
+                self.bring_to_front(id);
+                self.captured = Some(id);
+            }
+        }
+
+        let focused = self.focused;
+        let mut consumed = false;
+        for e in self.elements.iter_mut().rev() {
+            if !e.is_visible() {
+                continue;
+            }
+            let mut own_input = if mouse_owner == Some(e.id()) { input.clone() } else { input.without_mouse() };
+            if focused != Some(e.id()) {
+                own_input = own_input.without_keys();
+            }
+            consumed |= e.update(&own_input, &mut actions);
+        }
+
+        if !input.down {
+            self.captured = None;
+        }
+        self.fix_focus();
+        keyboard |= self.focused
+            .and_then(|id| self.elements.iter().find(|e| e.id() == id))
+            .is_some_and(|e| e.wants_keyboard());
+
+        UiResponse {
+            mouse: consumed || over_taskbar || mouse_owner.is_some(),
+            keyboard,
+            actions,
+        }
+    }
+
+    // bottom first, so the top window ends up drawn over the rest
+    pub fn draw(&self) {
+        for e in self.elements.iter() {
+            e.draw();
+        }
+        self.taskbar.draw(&self.elements, self.focused);
+    }
+}
+
+// Stacks widgets top to bottom, each getting the full width.
+// Returns the rect each widget ends up in.
+pub fn layout_column(widgets: &[Box<dyn Widget>], x: f32, y: f32, width: f32) -> Vec<Rect> {
+    let mut rects = Vec::with_capacity(widgets.len());
+    let mut cursor = y;
+    for widget in widgets.iter() {
+        let size = widget.size();
+        rects.push(Rect::new(x, cursor, width, size.y));
+        cursor += size.y + SPACING;
+    }
+    rects
+}
+
+pub fn column_size(widgets: &[Box<dyn Widget>]) -> Vec2 {
+    let mut size = Vec2::ZERO;
+    for (i, widget) in widgets.iter().enumerate() {
+        let w = widget.size();
+        size.x = size.x.max(w.x);
+        size.y += w.y + if i > 0 { SPACING } else { 0. };
+    }
+    size
+}
+
+// The classic 3D edge. Raised for buttons and window frames,
+// sunken for text boxes, lists and pressed buttons.
+pub fn draw_bevel(rect: Rect, raised: bool) {
+    let (light, dark) = if raised { (WIN98_HIGHLIGHT, WIN98_SHADOW) } else { (WIN98_SHADOW, WIN98_HIGHLIGHT) };
+    let Rect { x, y, w, h } = rect;
+    draw_line(x, y, x + w, y, 2., light);
+    draw_line(x, y, x, y + h, 2., light);
+    draw_line(x, y + h, x + w, y + h, 2., dark);
+    draw_line(x + w, y, x + w, y + h, 2., dark);
+    draw_line(x + 2., y + h - 2., x + w - 2., y + h - 2., 1., WIN98_SEMI_SHADOW);
+    draw_line(x + w - 2., y + 2., x + w - 2., y + h - 2., 1., WIN98_SEMI_SHADOW);
+}
+
+pub fn text_size(text: &str, font_size: u16) -> Vec2 {
+    let dims = measure_text(text, None, font_size, 1.0);
+    vec2(dims.width, font_size as f32)
+}
+
+// `y` is the top of the line, not the baseline like `draw_text`
+pub fn draw_ui_text(text: &str, x: f32, y: f32, font_size: u16, color: Color) {
+    draw_text_ex(
+        text,
+        x,
+        y + font_size as f32 * 0.75,
+        TextParams {
+            font_size,
+            color,
+            ..Default::default()
+        }
+    );
+}
@@ -0,0 +1,465 @@
+use macroquad::prelude::*;
+
+use super::ui::*;
+
+// Plain text, one line.
+pub struct Label {
+    pub text: String,
+    pub font_size: u16,
+    pub color: Color,
+}
+
+impl Label {
+    pub fn new(text: &str) -> Label {
+        Label {
+            text: String::from(text),
+            font_size: FONT_SIZE,
+            color: WIN98_TEXT,
+        }
+    }
+}
+
+impl Widget for Label {
+    fn size(&self) -> Vec2 {
+        text_size(&self.text, self.font_size)
+    }
+    fn draw(&self, rect: Rect) {
+        draw_ui_text(&self.text, rect.x, rect.y, self.font_size, self.color);
+    }
+}
+
+// A picture, drawn at its own size.
+pub struct Icon {
+    pub texture: Texture2D,
+}
+
+impl Widget for Icon {
+    fn size(&self) -> Vec2 {
+        vec2(self.texture.width(), self.texture.height())
+    }
+    fn draw(&self, rect: Rect) {
+        draw_texture(&self.texture, rect.x, rect.y, WHITE);
+    }
+}
+
+// Push button. Emits its action when released over it, like the
+// real thing (so you can still back out by dragging off).
+pub struct Button {
+    pub text: String,
+    pub action: UiAction,
+    held: bool,
+}
+
+impl Button {
+    pub fn new(text: &str, action: UiAction) -> Button {
+        Button {
+            text: String::from(text),
+            action,
+            held: false,
+        }
+    }
+}
+
+impl Widget for Button {
+    fn size(&self) -> Vec2 {
+        let text = text_size(&self.text, FONT_SIZE);
+        vec2((text.x + 24.).max(80.), text.y + 12.)
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        let hovered = rect.contains(input.mouse);
+        if input.pressed && hovered {
+            self.held = true;
+            return true;
+        }
+        if self.held && !input.down {
+            self.held = false;
+            if hovered {
+                actions.push(self.action.clone());
+            }
+            return true;
+        }
+        self.held
+    }
+    fn draw(&self, rect: Rect) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, WIN98_FACE);
+        draw_bevel(rect, !self.held);
+        let text = text_size(&self.text, FONT_SIZE);
+        // pressed buttons shift their label a pixel down-right
+        let nudge = if self.held { 1. } else { 0. };
+        draw_ui_text(
+            &self.text,
+            rect.x + (rect.w - text.x) / 2. + nudge,
+            rect.y + (rect.h - text.y) / 2. + nudge,
+            FONT_SIZE,
+            WIN98_TEXT,
+        );
+    }
+}
+
+const BOX_SIZE: f32 = 14.;
+
+pub struct Checkbox {
+    pub text: String,
+    pub checked: bool,
+    pub on_toggle: Option<fn(bool) -> UiAction>,
+}
+
+impl Checkbox {
+    pub fn new(text: &str, checked: bool, on_toggle: Option<fn(bool) -> UiAction>) -> Checkbox {
+        Checkbox {
+            text: String::from(text),
+            checked,
+            on_toggle,
+        }
+    }
+}
+
+impl Widget for Checkbox {
+    fn size(&self) -> Vec2 {
+        let text = text_size(&self.text, FONT_SIZE);
+        vec2(BOX_SIZE + SPACING + text.x, text.y.max(BOX_SIZE))
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        if input.pressed && rect.contains(input.mouse) {
+            self.checked = !self.checked;
+            if let Some(on_toggle) = self.on_toggle {
+                actions.push(on_toggle(self.checked));
+            }
+            return true;
+        }
+        false
+    }
+    fn draw(&self, rect: Rect) {
+        let bx = Rect::new(rect.x, rect.y + (rect.h - BOX_SIZE) / 2., BOX_SIZE, BOX_SIZE);
+        draw_rectangle(bx.x, bx.y, bx.w, bx.h, WIN98_FIELD);
+        draw_bevel(bx, false);
+        if self.checked {
+            draw_line(bx.x + 3., bx.y + 7., bx.x + 6., bx.y + 10., 2., WIN98_TEXT);
+            draw_line(bx.x + 6., bx.y + 10., bx.x + 11., bx.y + 3., 2., WIN98_TEXT);
+        }
+        draw_ui_text(&self.text, rect.x + BOX_SIZE + SPACING, rect.y, FONT_SIZE, WIN98_TEXT);
+    }
+}
+
+const THUMB_WIDTH: f32 = 10.;
+const TRACK_HEIGHT: f32 = 4.;
+
+// Horizontal slider over [min, max]. Label sits on the left.
+pub struct Slider {
+    pub text: String,
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    pub width: f32,
+    pub on_change: Option<fn(f32) -> UiAction>,
+    dragging: bool,
+}
+
+impl Slider {
+    pub fn new(text: &str, value: f32, min: f32, max: f32, on_change: Option<fn(f32) -> UiAction>) -> Slider {
+        Slider {
+            text: String::from(text),
+            value: value.clamp(min, max),
+            min,
+            max,
+            width: 140.,
+            on_change,
+            dragging: false,
+        }
+    }
+
+    fn track(&self, rect: Rect) -> Rect {
+        let label = text_size(&self.text, FONT_SIZE).x + SPACING;
+        Rect::new(rect.x + label, rect.y, rect.w - label, rect.h)
+    }
+}
+
+impl Widget for Slider {
+    fn size(&self) -> Vec2 {
+        let text = text_size(&self.text, FONT_SIZE);
+        vec2(text.x + SPACING + self.width, text.y + 6.)
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        let track = self.track(rect);
+        if input.pressed && track.contains(input.mouse) {
+            self.dragging = true;
+        }
+        if !self.dragging {
+            return false;
+        }
+        if !input.down {
+            self.dragging = false;
+            return true;
+        }
+        let t = ((input.mouse.x - track.x - THUMB_WIDTH / 2.) / (track.w - THUMB_WIDTH)).clamp(0., 1.);
+        let value = self.min + t * (self.max - self.min);
+        if value != self.value {
+            self.value = value;
+            if let Some(on_change) = self.on_change {
+                actions.push(on_change(value));
+            }
+        }
+        true
+    }
+    fn draw(&self, rect: Rect) {
+        draw_ui_text(&self.text, rect.x, rect.y + 3., FONT_SIZE, WIN98_TEXT);
+        let track = self.track(rect);
+        let groove = Rect::new(track.x, track.y + (track.h - TRACK_HEIGHT) / 2., track.w, TRACK_HEIGHT);
+        draw_bevel(groove, false);
+        let t = if self.max > self.min { (self.value - self.min) / (self.max - self.min) } else { 0. };
+        let thumb = Rect::new(track.x + t * (track.w - THUMB_WIDTH), track.y, THUMB_WIDTH, track.h);
+        draw_rectangle(thumb.x, thumb.y, thumb.w, thumb.h, WIN98_FACE);
+        draw_bevel(thumb, true);
+    }
+}
+
+const ROW_HEIGHT: f32 = FONT_SIZE as f32 + 4.;
+
+// Scrolling list. Click picks a row, the wheel scrolls.
+pub struct ListBox {
+    pub items: Vec<String>,
+    pub selected: Option<usize>,
+    pub rows: usize,
+    pub width: f32,
+    pub on_select: Option<fn(usize) -> UiAction>,
+    scroll: usize,
+}
+
+impl ListBox {
+    pub fn new(items: Vec<String>, rows: usize, on_select: Option<fn(usize) -> UiAction>) -> ListBox {
+        ListBox {
+            items,
+            selected: None,
+            rows,
+            width: 220.,
+            on_select,
+            scroll: 0,
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.items.len().saturating_sub(self.rows)
+    }
+}
+
+impl Widget for ListBox {
+    fn size(&self) -> Vec2 {
+        vec2(self.width, self.rows as f32 * ROW_HEIGHT + 4.)
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        if !rect.contains(input.mouse) {
+            return false;
+        }
+        if input.wheel > 0. {
+            self.scroll = self.scroll.saturating_sub(1);
+        } else if input.wheel < 0. {
+            self.scroll = (self.scroll + 1).min(self.max_scroll());
+        }
+        if input.pressed {
+            let row = ((input.mouse.y - rect.y - 2.) / ROW_HEIGHT) as usize;
+            let i = self.scroll + row;
+            if row < self.rows && i < self.items.len() {
+                self.selected = Some(i);
+                if let Some(on_select) = self.on_select {
+                    actions.push(on_select(i));
+                }
+            }
+        }
+        true
+    }
+    fn draw(&self, rect: Rect) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, WIN98_FIELD);
+        draw_bevel(rect, false);
+        for (row, i) in (self.scroll..self.items.len()).take(self.rows).enumerate() {
+            let y = rect.y + 2. + row as f32 * ROW_HEIGHT;
+            let color = if self.selected == Some(i) {
+                draw_rectangle(rect.x + 2., y, rect.w - 4., ROW_HEIGHT, WIN98_TITLE);
+                WIN98_FIELD
+            } else {
+                WIN98_TEXT
+            };
+            draw_ui_text(&self.items[i], rect.x + 4., y + 2., FONT_SIZE, color);
+        }
+        // only hint at the scrollbar, there's no dragging it
+        if self.max_scroll() > 0 {
+            let h = rect.h * self.rows as f32 / self.items.len() as f32;
+            let y = rect.y + (rect.h - h) * self.scroll as f32 / self.max_scroll() as f32;
+            draw_rectangle(rect.x + rect.w - 8., y, 6., h, WIN98_SEMI_SHADOW);
+        }
+    }
+}
+
+// One line of editable text. Click to focus, Enter submits.
+pub struct TextInput {
+    pub text: String,
+    pub width: f32,
+    pub max_len: usize,
+    pub focused: bool,
+    pub on_submit: Option<fn(String) -> UiAction>,
+}
+
+impl TextInput {
+    pub fn new(text: &str, on_submit: Option<fn(String) -> UiAction>) -> TextInput {
+        TextInput {
+            text: String::from(text),
+            width: 220.,
+            max_len: 32,
+            focused: false,
+            on_submit,
+        }
+    }
+}
+
+impl Widget for TextInput {
+    fn size(&self) -> Vec2 {
+        vec2(self.width, FONT_SIZE as f32 + 8.)
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        if input.pressed {
+            self.focused = rect.contains(input.mouse);
+        }
+        if !self.focused {
+            return false;
+        }
+        for c in input.chars.iter() {
+            if !c.is_control() && self.text.chars().count() < self.max_len {
+                self.text.push(*c);
+            }
+        }
+        if input.key_pressed(KeyCode::Backspace) {
+            self.text.pop();
+        }
+        if input.key_pressed(KeyCode::Enter) {
+            if let Some(on_submit) = self.on_submit {
+                actions.push(on_submit(self.text.clone()));
+            }
+        }
+        true
+    }
+    fn draw(&self, rect: Rect) {
+        draw_rectangle(rect.x, rect.y, rect.w, rect.h, WIN98_FIELD);
+        draw_bevel(rect, false);
+        draw_ui_text(&self.text, rect.x + 4., rect.y + 4., FONT_SIZE, WIN98_TEXT);
+        // blinking caret
+        if self.focused && (get_time() * 2.) as i64 % 2 == 0 {
+            let x = rect.x + 5. + text_size(&self.text, FONT_SIZE).x;
+            draw_line(x, rect.y + 4., x, rect.y + rect.h - 4., 1., WIN98_TEXT);
+        }
+    }
+    fn wants_keyboard(&self) -> bool {
+        self.focused
+    }
+    fn text_value(&self) -> Option<String> {
+        Some(self.text.clone())
+    }
+}
+
+// Lays its children out left to right, tops aligned.
+pub struct Row {
+    pub children: Vec<Box<dyn Widget>>,
+}
+
+impl Row {
+    fn rects(&self, rect: Rect) -> Vec<Rect> {
+        let mut x = rect.x;
+        self.children.iter().map(|child| {
+            let size = child.size();
+            let r = Rect::new(x, rect.y, size.x, size.y);
+            x += size.x + SPACING * 2.;
+            r
+        }).collect()
+    }
+}
+
+impl Widget for Row {
+    fn size(&self) -> Vec2 {
+        let mut size = Vec2::ZERO;
+        for (i, child) in self.children.iter().enumerate() {
+            let c = child.size();
+            size.x += c.x + if i > 0 { SPACING * 2. } else { 0. };
+            size.y = size.y.max(c.y);
+        }
+        size
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        let rects = self.rects(rect);
+        let mut consumed = false;
+        for (child, r) in self.children.iter_mut().zip(rects) {
+            consumed |= child.update(r, input, actions);
+        }
+        consumed
+    }
+    fn draw(&self, rect: Rect) {
+        for (child, r) in self.children.iter().zip(self.rects(rect)) {
+            child.draw(r);
+        }
+    }
+    fn wants_keyboard(&self) -> bool {
+        self.children.iter().any(|child| child.wants_keyboard())
+    }
+    fn text_value(&self) -> Option<String> {
+        self.children.iter().find_map(|child| child.text_value())
+    }
+}
+
+// Top to bottom, same as a window's own content.
+pub struct Column {
+    pub children: Vec<Box<dyn Widget>>,
+}
+
+impl Widget for Column {
+    fn size(&self) -> Vec2 {
+        column_size(&self.children)
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        let rects = layout_column(&self.children, rect.x, rect.y, rect.w);
+        let mut consumed = false;
+        for (child, r) in self.children.iter_mut().zip(rects) {
+            consumed |= child.update(r, input, actions);
+        }
+        consumed
+    }
+    fn draw(&self, rect: Rect) {
+        for (child, r) in self.children.iter().zip(layout_column(&self.children, rect.x, rect.y, rect.w)) {
+            child.draw(r);
+        }
+    }
+    fn wants_keyboard(&self) -> bool {
+        self.children.iter().any(|child| child.wants_keyboard())
+    }
+    fn text_value(&self) -> Option<String> {
+        self.children.iter().find_map(|child| child.text_value())
+    }
+}
+
+// Keeps its child at its natural size, centered in whatever
+// width it's given. For the OK/Cancel buttons, mostly.
+pub struct Centered {
+    pub child: Box<dyn Widget>,
+}
+
+impl Centered {
+    fn inner(&self, rect: Rect) -> Rect {
+        let size = self.child.size();
+        Rect::new(rect.x + (rect.w - size.x) / 2., rect.y, size.x, size.y)
+    }
+}
+
+impl Widget for Centered {
+    fn size(&self) -> Vec2 {
+        self.child.size()
+    }
+    fn update(&mut self, rect: Rect, input: &UiInput, actions: &mut Vec<UiAction>) -> bool {
+        let inner = self.inner(rect);
+        self.child.update(inner, input, actions)
+    }
+    fn draw(&self, rect: Rect) {
+        self.child.draw(self.inner(rect));
+    }
+    fn wants_keyboard(&self) -> bool {
+        self.child.wants_keyboard()
+    }
+    fn text_value(&self) -> Option<String> {
+        self.child.text_value()
+    }
+}